@@ -0,0 +1,64 @@
+// 不带图形界面直接用仿真库的示例, 运行 `cargo run --release --no-default-features --example headless_env [steps] [--lidar] [--vehicle car/truck/bus]`
+use std::time::SystemTime;
+
+use car_simulation::{MapKind, SPEED, env::{Action, Env}, exam::ExamEvent, lidar::LidarConfig, rng::Rng, vehicle::VehicleClass};
+
+// 随机动作跑steps步, 打印每秒步数; 无界面仿真要比实时快得多才能拿来训练, 每秒应该能跑几千步
+fn benchmark(steps: usize, lidar: bool, vehicle: VehicleClass) {
+    let mut env = Env::new();
+    env.vehicle = vehicle;
+    if lidar {
+        env.set_lidar(Some(LidarConfig::default()));
+    }
+    let mut rng = Rng::new(1);
+    let (mut episodes, mut passed, mut truncated, mut total_score, mut total_time) = (0, 0, 0, 0, 0.);
+    let mut observation = env.reset(MapKind::ALL[0], 0);
+    let lock = vehicle.spec().lock_turns;
+    let start = SystemTime::now();
+    for _ in 0..steps {
+        let action = Action {
+            steer_angle: rng.range(-lock, lock),
+            speed: rng.range(-SPEED, SPEED),
+        };
+        let (next, _, done, info) = env.step(action);
+        observation = next;
+        if done {
+            episodes += 1;
+            passed += info.events.contains(&ExamEvent::Parked) as i32;
+            truncated += info.truncated as i32;
+            total_score += info.score;
+            total_time += info.time;
+            observation = env.reset(MapKind::ALL[episodes % MapKind::ALL.len()], episodes as u64);
+        }
+    }
+    let elapsed = start.elapsed().unwrap().as_secs_f32();
+    println!("{} steps in {:.2}s ({:.0} steps/s)", steps, elapsed, steps as f32/elapsed);
+    if episodes > 0 {
+        println!("{} episodes: {} passed, {} truncated, mean score = {:.1}, mean time = {:.1}s",
+            episodes, passed, truncated, total_score as f32/episodes as f32, total_time/episodes as f32);
+    }
+    println!("last observation = {:?}", &observation.to_vec()[..5+observation.line_distances.len()]);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let steps = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(100000);
+    let vehicle = args.iter().position(|arg| arg == "--vehicle").and_then(|i| args.get(i+1))
+        .and_then(|name| VehicleClass::from_name(name)).unwrap_or(VehicleClass::Car);
+
+    let mut env = Env::new();
+    let observation = env.reset(MapKind::RightAngleTurn, 0);
+    println!("start at ({:.2}, {:.2}), heading = {:.2}rad", observation.x, observation.y, observation.heading);
+    // 直着往前开, 直到压线、碰撞或超时
+    let mut total_reward = 0.;
+    loop {
+        let (observation, reward, done, info) = env.step(Action { steer_angle: 0., speed: SPEED/2. });
+        total_reward += reward;
+        if done {
+            println!("finished after {:.1}s at ({:.2}, {:.2}): events = {}, score = {}, reward = {}",
+                info.time, observation.x, observation.y, info.events.len(), info.score, total_reward);
+            break;
+        }
+    }
+    benchmark(steps, args.iter().any(|arg| arg == "--lidar"), vehicle);
+}
@@ -0,0 +1,33 @@
+// 远程控制协议的本地客户端示例, 先运行 `cargo run -- --remote`, 再运行 `cargo run --example remote_client`
+use std::{io::{BufRead, BufReader, Write}, net::TcpStream, time::{Duration, SystemTime}};
+
+fn main() {
+    let port = std::env::args().nth(1).unwrap_or_else(|| "7878".to_string());
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut send = |line: &str| {
+        stream.write_all(line.as_bytes()).unwrap();
+        stream.write_all(b"\n").unwrap();
+    };
+    send(r#"{"cmd": "load_map", "map": "right_angle_turn"}"#);
+    send(r#"{"cmd": "gear", "value": "D"}"#);
+    send(r#"{"cmd": "throttle", "value": 0.3}"#);
+    let start = SystemTime::now();
+    let mut line = String::new();
+    let mut steered = false;
+    while start.elapsed().unwrap() < Duration::from_secs(5) {
+        line.clear();
+        if reader.read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        if !line.contains("\"type\":\"state\"") {
+            print!("{}", line);
+        }
+        if !steered && start.elapsed().unwrap() > Duration::from_secs(2) {
+            send(r#"{"cmd": "steer", "angle": 4}"#);
+            steered = true;
+        }
+    }
+    print!("last state: {}", line);
+    send(r#"{"cmd": "throttle", "value": 0}"#);
+}
@@ -0,0 +1,202 @@
+use crate::{Canvas, Point, View, CAR_HEIGHT, CAR_WIDTH, distance_of, geometry::{angle_of, cross, dot}, linear_algebra::Vector2D, obstacle::Obstacle};
+
+// 车辆在前方这个距离内有人或车就停下让行
+const VEHICLE_SAFE_DISTANCE: f32 = CAR_HEIGHT/2. + 3.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AgentKind {
+    Vehicle,
+    Pedestrian,
+}
+
+// 走完最后一个路点之后怎么办
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Route {
+    // 回到第一个路点重新出现, 用于单向车流
+    Respawn,
+    // 原路返回, 用于来回过马路的行人
+    PingPong,
+}
+
+// 按路点移动的交通参与者, 每帧和玩家的车一起更新
+#[derive(Clone)]
+pub struct Agent {
+    pub kind: AgentKind,
+    waypoints: Vec<Point>,
+    route: Route,
+    pub speed: f32,
+    // 到达每个路点后停留的时间(s)
+    pause: f32,
+    pub position: Point,
+    direction: Vector2D,
+    next: usize,
+    waiting: f32,
+}
+
+impl Agent {
+    pub fn new(kind: AgentKind, waypoints: Vec<Point>, route: Route, speed: f32, pause: f32) -> Self {
+        assert!(waypoints.len() >= 2);
+        // 相邻路点重合时一段路长度为0, 不停留又回到起点时update里走不完这一步
+        assert!(waypoints.windows(2).all(|pair| distance_of(pair[0], pair[1]) > 0.), "consecutive waypoints must differ");
+        let position = waypoints[0];
+        let direction = normalized(waypoints[1] - position);
+        Agent {
+            kind,
+            waypoints,
+            route,
+            speed,
+            pause,
+            position,
+            direction,
+            next: 1,
+            waiting: 0.,
+        }
+    }
+
+    pub fn heading(&self) -> f32 {
+        angle_of(self.direction) - std::f32::consts::PI/2.
+    }
+
+    pub fn obstacle(&self) -> Obstacle {
+        match self.kind {
+            AgentKind::Vehicle => Obstacle::vehicle(self.position, CAR_WIDTH, CAR_HEIGHT, self.heading()),
+            AgentKind::Pedestrian => Obstacle::Pedestrian(self.position),
+        }
+    }
+
+    // 车辆前方有blockers中的点时停车让行, 行人不让行
+    fn blocked(&self, blockers: &[Point]) -> bool {
+        if self.kind == AgentKind::Pedestrian {
+            return false;
+        }
+        blockers.iter().any(|p| {
+            let v = *p - self.position;
+            let ahead = dot(v, self.direction);
+            ahead > 0. && ahead < VEHICLE_SAFE_DISTANCE && cross(self.direction, v).abs() < CAR_WIDTH/2. + 0.5
+        })
+    }
+
+    pub fn update(&mut self, dt: f32, blockers: &[Point]) {
+        if self.waiting > 0. {
+            self.waiting -= dt;
+            return;
+        }
+        if self.blocked(blockers) {
+            return;
+        }
+        let mut step = self.speed*dt;
+        while step > 0. {
+            let target = self.waypoints[self.next];
+            let distance = distance_of(target, self.position);
+            if distance > step {
+                self.direction = normalized(target - self.position);
+                self.position = self.position + step*self.direction;
+                break;
+            }
+            self.position = target;
+            step -= distance;
+            self.advance();
+            if self.waiting > 0. {
+                break;
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        self.next += 1;
+        if self.next == self.waypoints.len() {
+            match self.route {
+                Route::Respawn => self.position = self.waypoints[0],
+                Route::PingPong => self.waypoints.reverse(),
+            }
+            self.next = 1;
+        }
+        self.direction = normalized(self.waypoints[self.next] - self.position);
+        self.waiting = self.pause;
+    }
+}
+
+fn normalized(v: Vector2D) -> Vector2D {
+    let length = (v.x()*v.x() + v.y()*v.y()).sqrt();
+    if length == 0. {
+        Vector2D::new_from_x_and_y(0., 1.)
+    } else {
+        (1./length)*v
+    }
+}
+
+impl View for Agent {
+    fn relative_translation(&self) -> Vector2D {
+        (0., 0.).into()
+    }
+
+    // 和同类的静态障碍物画法相同
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        self.obstacle().draw(canvas, translation + self.relative_translation());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Agent, AgentKind, Route};
+    use crate::{Point, distance_of, point2};
+
+    fn assert_at(agent: &Agent, p: Point) {
+        assert!(distance_of(agent.position, p) < 1e-4, "({}, {})", agent.position.x, agent.position.y);
+    }
+
+    // 沿x轴走到(10, 0)再往上到(10, 10)
+    fn corner() -> Vec<Point> {
+        vec![point2(0., 0.), point2(10., 0.), point2(10., 10.)]
+    }
+
+    #[test]
+    fn advance_through_waypoints() {
+        let mut agent = Agent::new(AgentKind::Pedestrian, corner(), Route::Respawn, 2., 0.);
+        agent.update(1., &[]);
+        assert_at(&agent, point2(2., 0.));
+        // 一步跨过拐角, 剩下的距离接着往下一个路点走
+        agent.update(5., &[]);
+        assert_at(&agent, point2(10., 2.));
+        assert!(agent.heading().abs() < 1e-4);
+        // 走到终点回到起点重新出现
+        agent.update(4., &[]);
+        assert_at(&agent, point2(0., 0.));
+        agent.update(1., &[]);
+        assert_at(&agent, point2(2., 0.));
+    }
+
+    #[test]
+    fn pause_and_ping_pong() {
+        let mut agent = Agent::new(AgentKind::Pedestrian, vec![point2(0., 0.), point2(4., 0.)], Route::PingPong, 1., 2.);
+        agent.update(4., &[]);
+        assert_at(&agent, point2(4., 0.));
+        // 在路点停留2s, 然后原路返回
+        agent.update(1.5, &[]);
+        assert_at(&agent, point2(4., 0.));
+        agent.update(0.5, &[]);
+        agent.update(1., &[]);
+        assert_at(&agent, point2(3., 0.));
+    }
+
+    #[test]
+    fn vehicles_yield() {
+        let waypoints = vec![point2(0., 0.), point2(100., 0.)];
+        let mut vehicle = Agent::new(AgentKind::Vehicle, waypoints.clone(), Route::Respawn, 5., 0.);
+        let mut pedestrian = Agent::new(AgentKind::Pedestrian, waypoints, Route::Respawn, 5., 0.);
+        let blocker = [point2(4., 0.)];
+        vehicle.update(1., &blocker);
+        pedestrian.update(1., &blocker);
+        assert_at(&vehicle, point2(0., 0.));
+        assert_at(&pedestrian, point2(5., 0.));
+        // 在后面或旁边车道上的不用让
+        vehicle.update(1., &[point2(-2., 0.), point2(4., 5.)]);
+        assert_at(&vehicle, point2(5., 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "consecutive waypoints must differ")]
+    fn repeated_waypoints() {
+        Agent::new(AgentKind::Pedestrian, vec![point2(0., 0.), point2(0., 0.)], Route::Respawn, 1., 0.);
+    }
+}
@@ -1,37 +1,68 @@
-use raqote::{DrawTarget, SolidSource};
+use crate::{Color, Canvas, Rect, point2, Car, geometry::Segment, obstacle::Obstacle, vehicle::VehicleSpec, Map, MapKind, WINDOW_WIDTH, WINDOW_HEIGHT, View, MENU_WIDTH};
 
-use crate::{CAR_HEIGHT, CAR_WIDTH, Rect, point2, Rotation, Car, MIRROR_HEIGHT, MIRROR_ANGLE, Map, WINDOW_WIDTH, WINDOW_HEIGHT, View, MENU_WIDTH};
-use lazy_static::lazy_static;
-
-const ROAD_WIDTH: f32 = CAR_HEIGHT * 1.5;
-const PARKING_LENGTH: f32 = CAR_HEIGHT+0.7;
-lazy_static! {
-    static ref PARKING_WIDTH: f32 = CAR_WIDTH+MIRROR_HEIGHT*2.*f32::sin(MIRROR_ANGLE)+0.6;
-}
 const MAP_WIDTH: f32 = WINDOW_WIDTH - MENU_WIDTH;
 const MAP_HEIGHT: f32 = WINDOW_HEIGHT;
 
 pub struct BackParking {
+    spec: VehicleSpec,
     road: Rect,
     parking_space: Rect,
 }
 
 impl BackParking {
-    pub fn new() -> Self {
-        let color = SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff);
-        let mut road = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT/2.), MAP_WIDTH, ROAD_WIDTH, Some(color));
+    // 道路宽为车长的1.5倍, 库长为车长加0.7m, 库宽为车宽(含后视镜)加0.6m
+    pub fn new(spec: VehicleSpec) -> Self {
+        let color = Color::rgb(0xff, 0xff, 0xff);
+        let road_width = spec.length*1.5;
+        let parking_length = spec.length+0.7;
+        let parking_width = spec.width+spec.mirror_span()+0.6;
+        // 大车的车库放在地图中间会超出下边界, 把道路往上移
+        let road_y = f32::max(MAP_HEIGHT/2., parking_length+road_width/2.+0.5);
+        let road = Rect::new(point2(MAP_WIDTH/2., road_y), MAP_WIDTH, road_width, Some(color));
         BackParking {
+            spec,
             road,
             parking_space: Rect::new(
-                point2(road.origin.x, road.origin.y-ROAD_WIDTH/2.-PARKING_LENGTH/2.),
-                *PARKING_WIDTH, PARKING_LENGTH, Some(color)),
+                point2(road.origin.x, road.origin.y-road_width/2.-parking_length/2.),
+                parking_width, parking_length, Some(color)),
         }
     }
 }
 
 impl Map for BackParking {
+    fn kind(&self) -> MapKind {
+        MapKind::BackParking
+    }
+
     fn car(&self) -> Car {
-        Car::new(point2(MAP_WIDTH-CAR_HEIGHT, self.road.origin.y), std::f32::consts::PI/2.)
+        Car::new(self.spec, point2(MAP_WIDTH-self.spec.length, self.road.origin.y), std::f32::consts::PI/2.)
+    }
+
+    fn lines(&self) -> Vec<Segment> {
+        let [top, _, _, _] = self.road.edges();
+        let [_, right, bottom, left] = self.parking_space.edges();
+        vec![
+            top,
+            Segment::new(self.road.lb(), self.parking_space.lt()),
+            Segment::new(self.parking_space.rt(), self.road.rb()),
+            left,
+            bottom,
+            right,
+        ]
+    }
+
+    fn goal(&self) -> Rect {
+        self.parking_space
+    }
+
+    // 车位底线后面的两个锥桶
+    fn obstacles(&self) -> Vec<Obstacle> {
+        let lb = self.parking_space.lb();
+        let rb = self.parking_space.rb();
+        vec![
+            Obstacle::Cone(point2(lb.x+0.3, lb.y-0.5)),
+            Obstacle::Cone(point2(rb.x-0.3, rb.y-0.5)),
+        ]
     }
 }
 
@@ -40,10 +71,10 @@ impl View for BackParking {
         (MENU_WIDTH, 0.).into()
     }
 
-    fn draw(&self, dt: &mut DrawTarget, translation: crate::linear_algebra::Vector2D) {
+    fn draw(&self, canvas: &mut dyn Canvas, translation: crate::linear_algebra::Vector2D) {
         let translation = self.relative_translation() + translation;
-        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0x00, 0x00, 0x00));
-        self.road.draw(dt, translation);
-        self.parking_space.draw(dt, translation);
+        canvas.clear(Color::rgb(0x00, 0x00, 0x00));
+        self.road.draw(canvas, translation);
+        self.parking_space.draw(canvas, translation);
     }
 }
\ No newline at end of file
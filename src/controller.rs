@@ -0,0 +1,514 @@
+use crate::{Car, Point, distance_of, geometry::{Segment, angle_of, cross, dot, normalize_angle}, linear_algebra::Vector2D};
+
+const GOAL_TOLERANCE: f32 = 0.2;
+const MAX_WHEEL_ANGLE: f32 = 40./180.*std::f32::consts::PI;
+
+#[derive(Clone, Copy)]
+pub struct Waypoint {
+    pub point: Point,
+    // 期望速度, <0 表示倒车
+    pub speed: f32,
+}
+
+#[derive(Clone)]
+pub struct Path {
+    waypoints: Vec<Waypoint>,
+}
+
+impl Path {
+    pub fn new(waypoints: Vec<Waypoint>) -> Self {
+        Path { waypoints }
+    }
+
+    // 按行驶方向把路径切成若干段, 每次换向(前进/倒车)为一个分界
+    fn segments(&self) -> Vec<std::ops::Range<usize>> {
+        let mut segments = vec![];
+        let mut start = 0;
+        for i in 1..self.waypoints.len() {
+            if (self.waypoints[i].speed < 0.) != (self.waypoints[start].speed < 0.) {
+                segments.push(start..i);
+                start = i;
+            }
+        }
+        if start < self.waypoints.len() {
+            segments.push(start..self.waypoints.len());
+        }
+        segments
+    }
+
+    fn polyline(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = Segment> + '_ {
+        let waypoints = &self.waypoints[range];
+        (0..waypoints.len().saturating_sub(1))
+            .map(move |i| Segment::new(waypoints[i].point, waypoints[i+1].point))
+    }
+
+    // p到整条路径的最短距离
+    pub fn distance_to(&self, p: Point) -> f32 {
+        match self.waypoints.len() {
+            0 => f32::INFINITY,
+            1 => distance_of(self.waypoints[0].point, p),
+            n => self.polyline(0..n).map(|s| s.distance_to(p)).fold(f32::INFINITY, f32::min),
+        }
+    }
+}
+
+// cross_track: 横向偏差, 车在路径左侧为正; heading: 路径切线与车行驶方向的夹角, 向左为正
+#[derive(Clone, Copy, Default)]
+pub struct TrackingError {
+    pub cross_track: f32,
+    pub heading: f32,
+}
+
+#[derive(Clone, Copy)]
+pub struct Command {
+    pub steer_angle: f32,
+    pub speed: f32,
+}
+
+pub trait Controller {
+    fn control(&mut self, car: &Car) -> Command;
+
+    // 最近一次control时的跟踪误差
+    fn error(&self) -> TrackingError;
+
+    fn finished(&self) -> bool;
+}
+
+// 记录当前跟踪到路径的哪一段、哪个点
+struct Tracker {
+    path: Path,
+    segments: Vec<std::ops::Range<usize>>,
+    segment: usize,
+    index: usize,
+    error: TrackingError,
+}
+
+impl Tracker {
+    fn new(path: Path) -> Self {
+        let segments = path.segments();
+        let index = segments.first().map(|s| s.start).unwrap_or(0);
+        Tracker { path, segments, segment: 0, index, error: TrackingError::default() }
+    }
+
+    fn finished(&self) -> bool {
+        self.segment >= self.segments.len()
+    }
+
+    fn range(&self) -> std::ops::Range<usize> {
+        self.segments[self.segment].clone()
+    }
+
+    fn reverse(&self) -> bool {
+        self.path.waypoints[self.range().start].speed < 0.
+    }
+
+    // 推进到离p最近的点, 到达本段终点后切换到下一段
+    fn update(&mut self, p: Point) {
+        while !self.finished() {
+            let range = self.range();
+            let mut best = distance_of(self.path.waypoints[self.index].point, p);
+            for i in self.index+1..range.end {
+                let d = distance_of(self.path.waypoints[i].point, p);
+                if d < best {
+                    best = d;
+                    self.index = i;
+                }
+            }
+            let end = self.path.waypoints[range.end-1].point;
+            // 到达终点附近, 或者已经越过终点
+            let passed = range.end - range.start >= 2
+                && dot(p - end, end - self.path.waypoints[range.end-2].point) > 0.;
+            if self.index + 1 >= range.end && (distance_of(end, p) < GOAL_TOLERANCE || passed) {
+                self.segment += 1;
+                if let Some(next) = self.segments.get(self.segment) {
+                    self.index = next.start;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn measure(&mut self, p: Point, motion: Vector2D) {
+        let range = self.range();
+        let mut best: Option<(f32, Segment)> = None;
+        for segment in self.path.polyline(self.index.saturating_sub(1).max(range.start)..range.end) {
+            let d = segment.distance_to(p);
+            if best.is_none_or(|(b, _)| d < b) {
+                best = Some((d, segment));
+            }
+        }
+        self.error = match best {
+            Some((_, segment)) => {
+                let tangent = segment.b - segment.a;
+                TrackingError {
+                    cross_track: cross(tangent.normalize(), p - segment.closest_point(p)),
+                    heading: normalize_angle(angle_of(tangent) - angle_of(motion)),
+                }
+            },
+            None => TrackingError {
+                cross_track: 0.,
+                heading: 0.,
+            },
+        };
+    }
+
+    // 当前点处路径的曲率, 按前后相邻两点算, 行驶方向向左转为正
+    fn curvature(&self) -> f32 {
+        let range = self.range();
+        if range.end - range.start < 3 {
+            return 0.;
+        }
+        let i = self.index.clamp(range.start + 1, range.end - 2);
+        let waypoints = &self.path.waypoints;
+        let (a, b, c) = (waypoints[i-1].point, waypoints[i].point, waypoints[i+1].point);
+        let d = distance_of(a, b)*distance_of(b, c)*distance_of(a, c);
+        if d > 0. {2.*cross(b - a, c - b)/d} else {0.}
+    }
+
+    fn stop(&self) -> Command {
+        Command { steer_angle: 0., speed: 0. }
+    }
+}
+
+fn motion_direction(car: &Car, reverse: bool) -> Vector2D {
+    if reverse {
+        -1. * car.direction()
+    } else {
+        car.direction()
+    }
+}
+
+// 行驶方向上的曲率转换成方向盘角度, 倒车时转向相反
+fn steer_for(car: &Car, curvature: f32, reverse: bool) -> f32 {
+    car.curvature2angle(if reverse {-curvature} else {curvature})
+}
+
+pub struct PurePursuit {
+    tracker: Tracker,
+    pub lookahead: f32,
+}
+
+impl PurePursuit {
+    pub fn new(path: Path, lookahead: f32) -> Self {
+        PurePursuit { tracker: Tracker::new(path), lookahead }
+    }
+}
+
+impl Controller for PurePursuit {
+    fn control(&mut self, car: &Car) -> Command {
+        let p = car.back_origin();
+        self.tracker.update(p);
+        if self.tracker.finished() {
+            return self.tracker.stop();
+        }
+        let reverse = self.tracker.reverse();
+        let motion = motion_direction(car, reverse);
+        self.tracker.measure(p, motion);
+        let range = self.tracker.range();
+        let waypoints = &self.tracker.path.waypoints;
+        let target = waypoints[self.tracker.index..range.end].iter()
+            .find(|w| distance_of(w.point, p) >= self.lookahead)
+            .unwrap_or(&waypoints[range.end-1]);
+        let ld = distance_of(target.point, p);
+        let curvature = if ld > 0. {
+            let alpha = normalize_angle(angle_of(target.point - p) - angle_of(motion));
+            2.*f32::sin(alpha)/ld
+        } else {
+            0.
+        };
+        Command {
+            steer_angle: steer_for(car, curvature, reverse),
+            speed: waypoints[self.tracker.index].speed,
+        }
+    }
+
+    fn error(&self) -> TrackingError {
+        self.tracker.error
+    }
+
+    fn finished(&self) -> bool {
+        self.tracker.finished()
+    }
+}
+
+pub struct Stanley {
+    tracker: Tracker,
+    pub gain: f32,
+    // 低速时防止除零
+    pub softening: f32,
+}
+
+impl Stanley {
+    pub fn new(path: Path, gain: f32, softening: f32) -> Self {
+        Stanley { tracker: Tracker::new(path), gain, softening }
+    }
+}
+
+impl Controller for Stanley {
+    fn control(&mut self, car: &Car) -> Command {
+        if self.tracker.finished() {
+            return self.tracker.stop();
+        }
+        let reverse = self.tracker.reverse();
+        // 前进时以前轴中点为参考, 倒车时以后轴中点为参考
+        let p = if reverse {car.back_origin()} else {car.top_origin()};
+        self.tracker.update(p);
+        if self.tracker.finished() {
+            return self.tracker.stop();
+        }
+        let motion = motion_direction(car, reverse);
+        self.tracker.measure(p, motion);
+        let speed = self.tracker.path.waypoints[self.tracker.index].speed;
+        let error = self.tracker.error;
+        let delta = (error.heading - f32::atan(self.gain*error.cross_track/(self.softening+speed.abs())))
+            .clamp(-MAX_WHEEL_ANGLE, MAX_WHEEL_ANGLE);
+        // 倒车时参考点在后轴, 航向误差补不了弯道需要的转角, 加上路径曲率作前馈, 否则弯道上一直偏着
+        let feedforward = if reverse {self.tracker.curvature()} else {0.};
+        Command {
+            steer_angle: steer_for(car, f32::tan(delta)/car.wheelbase() + feedforward, reverse),
+            speed,
+        }
+    }
+
+    fn error(&self) -> TrackingError {
+        self.tracker.error
+    }
+
+    fn finished(&self) -> bool {
+        self.tracker.finished()
+    }
+}
+
+// 录制实际行驶轨迹(后轴中点), 用于回放或与参考路径比较
+pub struct Recorder {
+    waypoints: Vec<Waypoint>,
+    spacing: f32,
+    // 上一帧后轴中点的位置
+    last: Option<Point>,
+}
+
+impl Recorder {
+    pub fn new(spacing: f32) -> Self {
+        Recorder { waypoints: vec![], spacing, last: None }
+    }
+
+    // 每帧调用一次, 行驶方向和速度按后轴中点的位移算, 自动驾驶或其他方式开车时也能录下来
+    pub fn record(&mut self, car: &Car, dt: f32) {
+        let point = car.back_origin();
+        let last = match self.last.replace(point) {
+            Some(last) => last,
+            None => return,
+        };
+        let moved = point - last;
+        let distance = distance_of(point, last);
+        if distance == 0. || dt <= 0. {
+            return;
+        }
+        let speed = distance/dt*dot(moved, car.direction()).signum();
+        if self.waypoints.is_empty() {
+            self.waypoints.push(Waypoint { point: last, speed });
+        }
+        let far_enough = self.waypoints.last()
+            .is_none_or(|w| distance_of(w.point, point) >= self.spacing || (w.speed < 0.) != (speed < 0.));
+        if far_enough {
+            self.waypoints.push(Waypoint { point, speed });
+        }
+    }
+
+    // 最后停下的位置不够一个间隔时也加上, 否则半路停下的那一段会少一截
+    pub fn finish(mut self) -> Path {
+        if let (Some(point), Some(&last)) = (self.last, self.waypoints.last()) {
+            if distance_of(last.point, point) > 0. {
+                self.waypoints.push(Waypoint { point, speed: last.speed });
+            }
+        }
+        Path::new(self.waypoints)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TrackingStats {
+    pub mean: f32,
+    pub rms: f32,
+    pub max: f32,
+}
+
+// 轨迹上每个点到参考路径的距离统计
+pub fn compare(reference: &Path, trajectory: &Path) -> TrackingStats {
+    let distances: Vec<f32> = trajectory.waypoints.iter()
+        .map(|w| reference.distance_to(w.point))
+        .collect();
+    if distances.is_empty() {
+        return TrackingStats { mean: 0., rms: 0., max: 0. };
+    }
+    let n = distances.len() as f32;
+    TrackingStats {
+        mean: distances.iter().sum::<f32>()/n,
+        rms: f32::sqrt(distances.iter().map(|d| d*d).sum::<f32>()/n),
+        max: distances.iter().cloned().fold(0., f32::max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Controller, Path, PurePursuit, Recorder, Stanley, TrackingError, Waypoint, compare};
+    use crate::{Car, distance_of, point2, vehicle::VehicleClass};
+
+    const DT: f32 = 0.05;
+    const SPEED: f32 = 2.;
+
+    // 沿y轴往前的直线
+    fn line() -> Path {
+        Path::new((0..=60).map(|i| Waypoint { point: point2(0., 0.5*(i as f32)), speed: SPEED }).collect())
+    }
+
+    // 从原点朝y轴出发, 绕(-radius, 0)向左转半圈
+    fn arc(radius: f32) -> Path {
+        let n = (std::f32::consts::PI*radius/0.5) as usize;
+        Path::new((0..=n).map(|i| {
+            let theta = 0.5*(i as f32)/radius;
+            Waypoint { point: point2(radius*(f32::cos(theta) - 1.), radius*f32::sin(theta)), speed: SPEED }
+        }).collect())
+    }
+
+    // 车头朝y轴, 沿y轴倒车
+    fn reverse_line() -> Path {
+        Path::new((0..=60).map(|i| Waypoint { point: point2(0., -0.5*(i as f32)), speed: -SPEED }).collect())
+    }
+
+    // 沿y轴往前开到(0, 15), 再绕(-radius, 15)倒车向左转四分之一圈, 像倒车入库
+    fn forward_then_reverse(radius: f32) -> Path {
+        let forward = (0..=30).map(|i| Waypoint { point: point2(0., 0.5*(i as f32)), speed: SPEED });
+        let n = (std::f32::consts::PI/2.*radius/0.5) as usize;
+        let reverse = (1..=n).map(|i| {
+            let theta = 0.5*(i as f32)/radius;
+            Waypoint { point: point2(radius*(f32::cos(theta) - 1.), 15. - radius*f32::sin(theta)), speed: -SPEED }
+        });
+        Path::new(forward.chain(reverse).collect())
+    }
+
+    // 后轴中点放在(x, 0), 车头朝y轴, 跟踪到路径结束; 返回每步的跟踪误差和录下的轨迹
+    fn drive(controller: &mut dyn Controller, x: f32) -> (Vec<TrackingError>, Path) {
+        let spec = VehicleClass::Car.spec();
+        let mut car = Car::new(spec, point2(x, spec.length/2. - spec.rear_overhang), 0.);
+        let mut recorder = Recorder::new(0.5);
+        let mut errors = vec![];
+        for _ in 0..2000 {
+            let command = controller.control(&car);
+            if controller.finished() {
+                break;
+            }
+            errors.push(controller.error());
+            car.set_steer_angle(command.steer_angle);
+            car.forward(command.speed*DT);
+            recorder.record(&car, DT);
+        }
+        assert!(controller.finished());
+        (errors, recorder.finish())
+    }
+
+    // 开头的偏差要在后半程消掉, 航向误差稳定在heading附近
+    fn assert_converges(errors: &[TrackingError], cross_track: f32, heading: f32, tolerance: f32) {
+        let tail = &errors[errors.len()/2..];
+        let max_cross_track = tail.iter().map(|e| e.cross_track.abs()).fold(0., f32::max);
+        let max_heading = tail.iter().map(|e| (e.heading - heading).abs()).fold(0., f32::max);
+        assert!(errors[0].cross_track.abs() > 0.4, "{}", errors[0].cross_track);
+        assert!(max_cross_track < cross_track, "cross track {}", max_cross_track);
+        assert!(max_heading < tolerance, "heading {}", max_heading);
+    }
+
+    #[test]
+    fn pure_pursuit_line() {
+        let (errors, trajectory) = drive(&mut PurePursuit::new(line(), 2.), 0.5);
+        assert_converges(&errors, 0.05, 0., 0.02);
+        assert!(compare(&line(), &trajectory).rms < 0.3);
+    }
+
+    #[test]
+    fn pure_pursuit_arc() {
+        let (errors, trajectory) = drive(&mut PurePursuit::new(arc(10.), 2.), 0.5);
+        assert_converges(&errors, 0.2, 0., 0.1);
+        assert!(compare(&arc(10.), &trajectory).rms < 0.3);
+    }
+
+    #[test]
+    fn stanley_line() {
+        let (errors, trajectory) = drive(&mut Stanley::new(line(), 1., 0.5), 0.5);
+        assert_converges(&errors, 0.05, 0., 0.02);
+        assert!(compare(&line(), &trajectory).rms < 0.3);
+    }
+
+    #[test]
+    fn stanley_arc() {
+        let (errors, trajectory) = drive(&mut Stanley::new(arc(10.), 1., 0.5), 0.5);
+        // Stanley按前轴中点算误差, 前轴在圆弧上时车身比切线少转asin(轴距/半径)
+        let spec = VehicleClass::Car.spec();
+        let wheelbase = spec.length - spec.front_overhang - spec.rear_overhang;
+        assert_converges(&errors, 0.2, f32::asin(wheelbase/10.), 0.05);
+        // 录的是后轴中点, 比前轴走在内侧
+        let inner = 10. - f32::sqrt(100. - wheelbase*wheelbase);
+        assert!(compare(&arc(10.), &trajectory).max < inner + 0.3);
+    }
+
+    #[test]
+    fn pure_pursuit_reverse_line() {
+        let (errors, trajectory) = drive(&mut PurePursuit::new(reverse_line(), 2.), 0.5);
+        assert_converges(&errors, 0.05, 0., 0.02);
+        assert!(compare(&reverse_line(), &trajectory).rms < 0.3);
+        assert!(trajectory.waypoints.iter().all(|w| w.speed < 0.));
+    }
+
+    #[test]
+    fn stanley_reverse_line() {
+        let (errors, trajectory) = drive(&mut Stanley::new(reverse_line(), 1., 0.5), 0.5);
+        assert_converges(&errors, 0.05, 0., 0.02);
+        assert!(compare(&reverse_line(), &trajectory).rms < 0.3);
+        assert!(trajectory.waypoints.iter().all(|w| w.speed < 0.));
+    }
+
+    // 前进一段再倒车, 录下的轨迹也是前进和倒车两段, 最后停在路径终点附近
+    fn assert_forward_then_reverse(trajectory: &Path, rms: f32, max: f32) {
+        let path = forward_then_reverse(10.);
+        assert_eq!(trajectory.segments().len(), 2);
+        assert!(trajectory.waypoints[0].speed > 0.);
+        assert!(trajectory.waypoints.last().unwrap().speed < 0.);
+        let stats = compare(&path, trajectory);
+        assert!(stats.rms < rms && stats.max < max, "rms {} max {}", stats.rms, stats.max);
+        let end = path.waypoints.last().unwrap().point;
+        assert!(distance_of(trajectory.waypoints.last().unwrap().point, end) < 0.5);
+    }
+
+    #[test]
+    fn pure_pursuit_forward_then_reverse() {
+        let (_, trajectory) = drive(&mut PurePursuit::new(forward_then_reverse(10.), 2.), 0.5);
+        assert_forward_then_reverse(&trajectory, 0.2, 0.55);
+    }
+
+    #[test]
+    fn stanley_forward_then_reverse() {
+        let (errors, trajectory) = drive(&mut Stanley::new(forward_then_reverse(10.), 1., 0.5), 0.5);
+        // 前进段是前轴到达换向点, 开始倒车时后轴离倒车的弧线差着一个轴距, 要在倒车段里追回来
+        assert_forward_then_reverse(&trajectory, 0.4, 0.8);
+        assert!(errors[errors.len()*9/10..].iter().all(|e| e.cross_track.abs() < 0.2));
+    }
+
+    #[test]
+    fn recorder_direction() {
+        let spec = VehicleClass::Car.spec();
+        let mut car = Car::new(spec, point2(0., spec.length/2. - spec.rear_overhang + 1.), 0.);
+        let mut recorder = Recorder::new(0.5);
+        recorder.record(&car, DT);
+        for speed in [SPEED, -SPEED].iter() {
+            for _ in 0..40 {
+                car.forward(speed*DT);
+                recorder.record(&car, DT);
+            }
+        }
+        let path = recorder.finish();
+        // 前进和倒车各是一段, 速度按位移算
+        assert_eq!(path.segments().len(), 2);
+        assert!(path.waypoints.iter().all(|w| (w.speed.abs() - SPEED).abs() < 1e-3));
+        assert!(compare(&line(), &path).max < 1e-3);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{Color, Canvas, CAR_HEIGHT, Rect, point2, Car, geometry::Segment, agent::{Agent, AgentKind, Route}, vehicle::VehicleSpec, Map, MapKind, WINDOW_WIDTH, WINDOW_HEIGHT, MENU_WIDTH, View};
+
+const LANE_WIDTH: f32 = 3.5;
+const ROAD_WIDTH: f32 = LANE_WIDTH * 2.;
+const CROSSWALK_WIDTH: f32 = 4.;
+const STRIPE_WIDTH: f32 = 0.45;
+const MAP_WIDTH: f32 = WINDOW_WIDTH - MENU_WIDTH;
+const MAP_HEIGHT: f32 = WINDOW_HEIGHT;
+const PEDESTRIAN_SPEED: f32 = 1.2;
+const TRAFFIC_SPEED: f32 = 5.;
+
+// 双车道城市道路, 中间有人行横道: 有行人来回过马路, 对向车道有车驶来, 开到前方停车
+pub struct Crossing {
+    spec: VehicleSpec,
+    road: Rect,
+    crosswalk: Rect,
+}
+
+impl Crossing {
+    pub fn new(spec: VehicleSpec) -> Self {
+        let color = Color::rgb(0xff, 0xff, 0xff);
+        let road = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT/2.), ROAD_WIDTH, MAP_HEIGHT, Some(color));
+        Crossing {
+            spec,
+            road,
+            crosswalk: Rect::new(point2(road.origin.x, MAP_HEIGHT*0.6), ROAD_WIDTH, CROSSWALK_WIDTH, None),
+        }
+    }
+}
+
+impl Map for Crossing {
+    fn kind(&self) -> MapKind {
+        MapKind::Crossing
+    }
+
+    fn car(&self) -> Car {
+        Car::new(self.spec, point2(self.road.origin.x+LANE_WIDTH/2., self.spec.length/2.+1.), 0.)
+    }
+
+    fn lines(&self) -> Vec<Segment> {
+        let [_, right, _, left] = self.road.edges();
+        vec![left, right]
+    }
+
+    fn goal(&self) -> Rect {
+        Rect::new(point2(self.road.origin.x+LANE_WIDTH/2., MAP_HEIGHT-self.spec.length-1.),
+            LANE_WIDTH, self.spec.length+1., None)
+    }
+
+    fn agents(&self) -> Vec<Agent> {
+        let y = self.crosswalk.origin.y;
+        let left = self.road.origin.x-ROAD_WIDTH/2.-1.5;
+        let right = self.road.origin.x+ROAD_WIDTH/2.+1.5;
+        let lane = self.road.origin.x-LANE_WIDTH/2.;
+        vec![
+            Agent::new(AgentKind::Pedestrian, vec![point2(left, y), point2(right, y)],
+                Route::PingPong, PEDESTRIAN_SPEED, 3.),
+            Agent::new(AgentKind::Vehicle, vec![point2(lane, MAP_HEIGHT+CAR_HEIGHT), point2(lane, -CAR_HEIGHT)],
+                Route::Respawn, TRAFFIC_SPEED, 2.),
+        ]
+    }
+}
+
+impl View for Crossing {
+    fn relative_translation(&self) -> crate::linear_algebra::Vector2D {
+        (MENU_WIDTH, 0.).into()
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas, translation: crate::linear_algebra::Vector2D) {
+        let translation = self.relative_translation() + translation;
+        canvas.clear(Color::rgb(0x00, 0x00, 0x00));
+        self.road.draw(canvas, translation);
+        // 黄色中心线
+        Rect::new(self.road.origin, 0.15, MAP_HEIGHT, Some(Color::rgb(0xf0, 0xc0, 0x00)))
+            .draw(canvas, translation);
+        // 斑马线
+        let stripe_color = Color::rgb(0xa0, 0xa0, 0xa0);
+        let count = (ROAD_WIDTH/(STRIPE_WIDTH*2.)) as usize;
+        let left = self.crosswalk.lt().x + STRIPE_WIDTH;
+        for i in 0..count {
+            Rect::new(point2(left + (i as f32)*STRIPE_WIDTH*2., self.crosswalk.origin.y),
+                STRIPE_WIDTH, CROSSWALK_WIDTH, Some(stripe_color)).draw(canvas, translation);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use car_simulation::json::Value;
+
+// 模拟量输入轴
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Axis {
+    Steering,
+    Throttle,
+    Brake,
+    Clutch,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 4] = [Axis::Steering, Axis::Throttle, Axis::Brake, Axis::Clutch];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::Steering => "steering",
+            Axis::Throttle => "throttle",
+            Axis::Brake => "brake",
+            Axis::Clutch => "clutch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Axis> {
+        Axis::ALL.iter().cloned().find(|axis| axis.name() == name)
+    }
+
+    fn index(self) -> usize {
+        Axis::ALL.iter().position(|&axis| axis == self).unwrap()
+    }
+
+    // 踏板的取值是[0, 1], 方向是[-1, 1]
+    fn is_pedal(self) -> bool {
+        self != Axis::Steering
+    }
+}
+
+// 输入设备, 只负责给出各轴的原始读数: 方向[-1, 1]向左为正, 踏板[0, 1]踩到底为1
+pub trait InputDevice {
+    // 每帧调用一次, 读走设备上积攒的事件
+    fn poll(&mut self, dt: f32);
+
+    fn raw(&self, axis: Axis) -> f32;
+}
+
+// 一个轴的死区和响应曲线
+#[derive(Clone, Copy)]
+pub struct AxisConfig {
+    // 原始值绝对值小于这个数时当作0, 用来消除方向盘回中不准和踏板虚位
+    pub deadzone: f32,
+    // 去掉死区后的值取curve次方, 大于1时中间更细腻
+    pub curve: f32,
+    // 方向反了(方向向右为正, 或踏板松开时读数为1)
+    pub invert: bool,
+}
+
+impl Default for AxisConfig {
+    fn default() -> Self {
+        AxisConfig {
+            deadzone: 0.05,
+            curve: 1.,
+            invert: false,
+        }
+    }
+}
+
+impl AxisConfig {
+    fn from_json(value: &Value) -> Result<AxisConfig, String> {
+        let default = AxisConfig::default();
+        let number = |key: &str, default: f32| match value.get(key) {
+            Some(v) => v.as_f32().ok_or(format!("{} must be a number", key)),
+            None => Ok(default),
+        };
+        Ok(AxisConfig {
+            deadzone: number("deadzone", default.deadzone)?,
+            curve: number("curve", default.curve)?,
+            invert: match value.get("invert") {
+                Some(v) => v.as_bool().ok_or("invert must be a boolean")?,
+                None => default.invert,
+            },
+        })
+    }
+
+    pub fn apply(&self, raw: f32, pedal: bool) -> f32 {
+        let raw = match (self.invert, pedal) {
+            (false, _) => raw,
+            (true, false) => -raw,
+            (true, true) => 1. - raw,
+        };
+        let magnitude = ((raw.abs() - self.deadzone)/(1. - self.deadzone)).clamp(0., 1.);
+        raw.signum()*magnitude.powf(self.curve)
+    }
+}
+
+// 死区和曲线处理之后的各轴读数
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AnalogState {
+    pub steering: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub clutch: f32,
+}
+
+impl AnalogState {
+    // 踏板对应的车速比例: 油门减刹车, 离合踩下去时动力断开
+    pub fn drive(&self) -> f32 {
+        (self.throttle - self.brake).max(0.)*(1. - self.clutch)
+    }
+}
+
+// 输入设备加上每个轴的处理配置
+pub struct DeviceInput {
+    device: Box<dyn InputDevice>,
+    axes: [AxisConfig; 4],
+}
+
+impl DeviceInput {
+    pub fn new(device: Box<dyn InputDevice>, axes: [AxisConfig; 4]) -> Self {
+        DeviceInput { device, axes }
+    }
+
+    // 配置文件的"device"部分:
+    //   {"type": "joystick", "path": "/dev/input/js0",
+    //    "axes": {"steering": {"index": 0, "deadzone": 0.02, "curve": 1.5}, "throttle": {"index": 2, "invert": true}}}
+    //   {"type": "virtual", "script": [{"t": 0, "throttle": 0}, {"t": 2, "throttle": 0.5, "steering": 0.3}]}
+    // 手柄的每个轴用index指定设备上的轴号, 没写的轴读数为0
+    pub fn from_json(value: &Value) -> Result<DeviceInput, String> {
+        let mut axes = [AxisConfig::default(); 4];
+        let mut indices = vec![];
+        if let Some(fields) = value.get("axes") {
+            let fields = match fields {
+                Value::Object(fields) => fields,
+                _ => return Err("axes must be an object".to_string()),
+            };
+            for (name, config) in fields.iter() {
+                let axis = Axis::from_name(name).ok_or(format!("unknown axis: {}", name))?;
+                axes[axis.index()] = AxisConfig::from_json(config)?;
+                if let Some(index) = config.get("index") {
+                    indices.push((axis, index.as_f32().ok_or("index must be a number")? as u8));
+                }
+            }
+        }
+        let device: Box<dyn InputDevice> = match value.get("type").and_then(Value::as_str) {
+            Some("virtual") => {
+                let script = value.get("script").ok_or("virtual device needs a script")?;
+                Box::new(VirtualDevice::from_json(script)?)
+            },
+            Some("joystick") => {
+                let path = value.get("path").and_then(Value::as_str).unwrap_or(JOYSTICK_PATH);
+                open_joystick(path, indices)?
+            },
+            _ => return Err("device type must be joystick/virtual".to_string()),
+        };
+        Ok(DeviceInput::new(device, axes))
+    }
+
+    pub fn poll(&mut self, dt: f32) -> AnalogState {
+        self.device.poll(dt);
+        let value = |axis: Axis| self.axes[axis.index()].apply(self.device.raw(axis), axis.is_pedal());
+        AnalogState {
+            steering: value(Axis::Steering),
+            throttle: value(Axis::Throttle),
+            brake: value(Axis::Brake),
+            clutch: value(Axis::Clutch),
+        }
+    }
+}
+
+// 按脚本回放的虚拟设备, 关键帧之间线性插值, 不用接硬件就能试映射和控制逻辑
+pub struct VirtualDevice {
+    // (时间, 各轴原始读数), 按时间排好序
+    keyframes: Vec<(f32, [f32; 4])>,
+    time: f32,
+}
+
+impl VirtualDevice {
+    pub fn new(keyframes: Vec<(f32, [f32; 4])>) -> Self {
+        VirtualDevice { keyframes, time: 0. }
+    }
+
+    // 关键帧数组, 每帧有时间t和若干轴的读数, 没写的轴沿用上一帧
+    pub fn from_json(value: &Value) -> Result<VirtualDevice, String> {
+        let frames = value.as_array().ok_or("script must be an array")?;
+        let mut keyframes: Vec<(f32, [f32; 4])> = vec![];
+        for frame in frames.iter() {
+            let time = frame.get("t").and_then(Value::as_f32).ok_or("keyframe needs a time t")?;
+            if keyframes.last().is_some_and(|&(last, _)| time < last) {
+                return Err("keyframes must be in time order".to_string());
+            }
+            let mut values = keyframes.last().map_or([0.; 4], |&(_, values)| values);
+            for axis in Axis::ALL.iter() {
+                if let Some(v) = frame.get(axis.name()) {
+                    values[axis.index()] = v.as_f32().ok_or(format!("{} must be a number", axis.name()))?;
+                }
+            }
+            keyframes.push((time, values));
+        }
+        Ok(VirtualDevice::new(keyframes))
+    }
+}
+
+impl InputDevice for VirtualDevice {
+    fn poll(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn raw(&self, axis: Axis) -> f32 {
+        let i = axis.index();
+        let next = self.keyframes.iter().position(|&(time, _)| time > self.time);
+        match next {
+            None => self.keyframes.last().map_or(0., |(_, values)| values[i]),
+            Some(0) => self.keyframes[0].1[i],
+            Some(next) => {
+                let (t0, v0) = self.keyframes[next-1];
+                let (t1, v1) = self.keyframes[next];
+                v0[i] + (v1[i] - v0[i])*(self.time - t0)/(t1 - t0)
+            },
+        }
+    }
+}
+
+const JOYSTICK_PATH: &str = "/dev/input/js0";
+
+#[cfg(target_os = "linux")]
+fn open_joystick(path: &str, axes: Vec<(Axis, u8)>) -> Result<Box<dyn InputDevice>, String> {
+    Ok(Box::new(Joystick::open(path, axes).map_err(|err| format!("cannot open {}: {}", path, err))?))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_joystick(_: &str, _: Vec<(Axis, u8)>) -> Result<Box<dyn InputDevice>, String> {
+    Err("joystick is only supported on Linux".to_string())
+}
+
+// Linux joystick接口(/dev/input/jsN), 方向盘和踏板套件也是这个接口
+#[cfg(target_os = "linux")]
+pub struct Joystick {
+    file: std::fs::File,
+    // 每个轴对应的设备轴号
+    axes: Vec<(Axis, u8)>,
+    values: [f32; 4],
+}
+
+#[cfg(target_os = "linux")]
+impl Joystick {
+    pub fn open(path: &str, axes: Vec<(Axis, u8)>) -> std::io::Result<Joystick> {
+        use std::os::unix::fs::OpenOptionsExt;
+        const O_NONBLOCK: i32 = 0o4000;
+        let file = std::fs::OpenOptions::new().read(true).custom_flags(O_NONBLOCK).open(path)?;
+        // 没收到事件之前方向回中, 踏板松开
+        Ok(Joystick { file, axes, values: [0.; 4] })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl InputDevice for Joystick {
+    fn poll(&mut self, _: f32) {
+        use std::io::Read;
+        // struct js_event { u32 time; i16 value; u8 type; u8 number; }
+        const EVENT_SIZE: usize = 8;
+        const JS_EVENT_AXIS: u8 = 0x02;
+        const JS_EVENT_INIT: u8 = 0x80;
+        let mut buffer = [0u8; EVENT_SIZE*32];
+        loop {
+            let n = match self.file.read(&mut buffer) {
+                Ok(n) if n > 0 => n,
+                _ => break,
+            };
+            for event in buffer[..n].chunks_exact(EVENT_SIZE) {
+                let value = i16::from_ne_bytes([event[4], event[5]]) as f32/32767.;
+                if event[6] & !JS_EVENT_INIT != JS_EVENT_AXIS {
+                    continue;
+                }
+                for &(axis, number) in self.axes.iter() {
+                    if number == event[7] {
+                        // 踏板从-32767(松开)到32767(踩到底)
+                        self.values[axis.index()] = if axis.is_pedal() {(value + 1.)/2.} else {value};
+                    }
+                }
+            }
+        }
+    }
+
+    fn raw(&self, axis: Axis) -> f32 {
+        self.values[axis.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Axis, AxisConfig, DeviceInput, InputDevice, VirtualDevice};
+    use car_simulation::json::Value;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn deadzone() {
+        let config = AxisConfig { deadzone: 0.1, curve: 1., invert: false };
+        assert_eq!(config.apply(0.05, false), 0.);
+        assert_eq!(config.apply(-0.1, false), 0.);
+        // 死区之外重新拉伸到[0, 1], 打满仍然是1
+        assert_near(config.apply(0.55, false), 0.5);
+        assert_near(config.apply(-0.55, false), -0.5);
+        assert_near(config.apply(1., false), 1.);
+        assert_near(config.apply(1.2, false), 1.);
+    }
+
+    #[test]
+    fn curve() {
+        let config = AxisConfig { deadzone: 0., curve: 2., invert: false };
+        assert_near(config.apply(0.5, false), 0.25);
+        assert_near(config.apply(-0.5, false), -0.25);
+        assert_near(config.apply(1., false), 1.);
+    }
+
+    #[test]
+    fn invert() {
+        let config = AxisConfig { deadzone: 0., curve: 1., invert: true };
+        // 方向取反, 踏板按松开为1算
+        assert_near(config.apply(0.3, false), -0.3);
+        assert_near(config.apply(1., true), 0.);
+        assert_near(config.apply(0.25, true), 0.75);
+    }
+
+    #[test]
+    fn keyframes() {
+        let mut device = VirtualDevice::new(vec![
+            (1., [0., 0., 0., 0.]),
+            (3., [1., 0.5, 0., 0.]),
+        ]);
+        // 第一帧之前保持第一帧, 关键帧之间线性插值, 最后一帧之后保持最后一帧
+        assert_eq!(device.raw(Axis::Steering), 0.);
+        device.poll(2.);
+        assert_near(device.raw(Axis::Steering), 0.5);
+        assert_near(device.raw(Axis::Throttle), 0.25);
+        device.poll(5.);
+        assert_near(device.raw(Axis::Steering), 1.);
+        assert_near(device.raw(Axis::Throttle), 0.5);
+    }
+
+    #[test]
+    fn script() {
+        let config = Value::parse(r#"{"type": "virtual",
+            "axes": {"steering": {"deadzone": 0}, "throttle": {"deadzone": 0}, "brake": {"deadzone": 0}, "clutch": {"deadzone": 0}},
+            "script": [{"t": 0, "throttle": 0, "steering": 0},
+                       {"t": 2, "throttle": 0.8, "steering": -0.5},
+                       {"t": 4, "brake": 0.8},
+                       {"t": 5, "brake": 0, "clutch": 1}]}"#).unwrap();
+        let mut device = DeviceInput::from_json(&config).unwrap();
+        let state = device.poll(1.);
+        assert_near(state.steering, -0.25);
+        assert_near(state.drive(), 0.4);
+        // 没写的轴沿用上一帧: 刹车从0踩到0.8时油门还是0.8
+        let state = device.poll(2.);
+        assert_near(state.throttle, 0.8);
+        assert_near(state.brake, 0.4);
+        assert_near(state.drive(), 0.4);
+        // 刹车踩得和油门一样深时不走
+        let state = device.poll(1.);
+        assert_near(state.drive(), 0.);
+        // 离合踩到底时动力断开
+        let state = device.poll(1.);
+        assert_eq!(state.brake, 0.);
+        assert_near(state.drive(), 0.);
+    }
+}
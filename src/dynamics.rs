@@ -0,0 +1,223 @@
+use crate::{Car, Rotation, new_rotation_matrix};
+
+const GRAVITY: f32 = 9.81;
+// 低于这个车速(或倒车)时侧偏角没有意义, 退回运动学模型
+const KINEMATIC_SPEED: f32 = 2.;
+// 积分步长上限(s), 轮胎力的时间常数在0.1s左右
+const MAX_STEP: f32 = 0.002;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TireModel {
+    // 侧向力和侧偏角成正比
+    Linear,
+    // Pacejka魔术公式, 侧向力在大侧偏角时饱和
+    Pacejka,
+}
+
+impl TireModel {
+    pub const ALL: [TireModel; 2] = [TireModel::Linear, TireModel::Pacejka];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TireModel::Linear => "linear",
+            TireModel::Pacejka => "pacejka",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TireModel> {
+        TireModel::ALL.iter().cloned().find(|model| model.name() == name)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DynamicsConfig {
+    pub tire: TireModel,
+    // 整车质量(kg)和绕质心的转动惯量(kg·m²)
+    pub mass: f32,
+    pub yaw_inertia: f32,
+    // 前轴承担的重量比例, 决定质心在轴距上的位置
+    pub front_weight: f32,
+    // 前后轴的侧偏刚度(N/rad)
+    pub front_stiffness: f32,
+    pub rear_stiffness: f32,
+    // 路面附着系数, 只用于Pacejka
+    pub friction: f32,
+    // Pacejka的形状系数C和曲率系数E, 刚度系数B由侧偏刚度算出
+    pub shape: f32,
+    pub curvature: f32,
+}
+
+impl DynamicsConfig {
+    pub fn new(tire: TireModel) -> Self {
+        DynamicsConfig {
+            tire,
+            mass: 1800.,
+            yaw_inertia: 3000.,
+            front_weight: 0.5,
+            front_stiffness: 80000.,
+            rear_stiffness: 90000.,
+            friction: 0.9,
+            shape: 1.9,
+            curvature: 0.97,
+        }
+    }
+
+    // 一根轴的侧向力(N), 向左为正
+    fn lateral_force(&self, slip_angle: f32, stiffness: f32, load: f32) -> f32 {
+        match self.tire {
+            TireModel::Linear => stiffness*slip_angle,
+            TireModel::Pacejka => {
+                let d = self.friction*load;
+                let b = stiffness/(self.shape*d);
+                let x = b*slip_angle;
+                d*f32::sin(self.shape*f32::atan(x - self.curvature*(x - f32::atan(x))))
+            },
+        }
+    }
+}
+
+// 动力学单车模型: 纵向车速按指令, 侧向速度和横摆角速度由前后轴的轮胎侧向力决定
+pub struct BicycleModel {
+    pub config: DynamicsConfig,
+    // 质心处的侧向速度(m/s, 向左为正)和横摆角速度(rad/s, 逆时针为正)
+    pub lateral_speed: f32,
+    pub yaw_rate: f32,
+}
+
+impl BicycleModel {
+    pub fn new(config: DynamicsConfig) -> Self {
+        BicycleModel {
+            config,
+            lateral_speed: 0.,
+            yaw_rate: 0.,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.lateral_speed = 0.;
+        self.yaw_rate = 0.;
+    }
+
+    // 质心处的侧偏角
+    pub fn slip_angle(&self, speed: f32) -> f32 {
+        if speed.abs() < KINEMATIC_SPEED {
+            0.
+        } else {
+            f32::atan2(self.lateral_speed, speed.abs())
+        }
+    }
+
+    // 以纵向车速speed行驶dt秒
+    pub fn step(&mut self, car: &mut Car, speed: f32, dt: f32) {
+        if speed == 0. {
+            self.reset();
+            return;
+        }
+        let wheelbase = car.wheelbase();
+        let to_front = wheelbase*(1.-self.config.front_weight);
+        let to_rear = wheelbase - to_front;
+        let curvature = car.steer_angle/car.steer_ratio();
+        let wheel_angle = f32::atan(wheelbase*curvature);
+        // 后轮转向时转向中心在后轴前方rear_offset处
+        let rear_offset = -car.rear_ratio*wheelbase/(1.-car.rear_ratio);
+        if speed < KINEMATIC_SPEED {
+            // 不打滑: 和运动学模型一样按前轴走过的距离绕转向中心转动,
+            // 记下横摆角速度和侧向速度, 加速以后从这里接着积分
+            self.yaw_rate = speed*curvature*(1.-car.rear_ratio)*wheel_angle.cos();
+            self.lateral_speed = self.yaw_rate*(to_rear-rear_offset);
+            car.forward(speed*dt);
+            return;
+        }
+        let c = &self.config;
+        let rear_wheel_angle = f32::atan(car.rear_ratio*wheelbase*curvature);
+        let front_load = c.mass*GRAVITY*c.front_weight;
+        let rear_load = c.mass*GRAVITY - front_load;
+        let mut remaining = dt;
+        while remaining > 0. {
+            let h = remaining.min(MAX_STEP);
+            let front_slip = wheel_angle - f32::atan((self.lateral_speed + to_front*self.yaw_rate)/speed);
+            let rear_slip = rear_wheel_angle - f32::atan((self.lateral_speed - to_rear*self.yaw_rate)/speed);
+            let front_force = c.lateral_force(front_slip, c.front_stiffness, front_load)*wheel_angle.cos();
+            let rear_force = c.lateral_force(rear_slip, c.rear_stiffness, rear_load)*rear_wheel_angle.cos();
+            self.lateral_speed += h*((front_force + rear_force)/c.mass - speed*self.yaw_rate);
+            self.yaw_rate += h*(to_front*front_force - to_rear*rear_force)/c.yaw_inertia;
+            self.advance(car, speed, to_rear, h);
+            remaining -= h;
+        }
+    }
+
+    // 质心按当前速度平移, 再绕质心转动
+    fn advance(&self, car: &mut Car, speed: f32, to_rear: f32, dt: f32) {
+        let direction = car.direction();
+        let left = new_rotation_matrix(std::f32::consts::PI/2.)*direction;
+        car.translate(dt*(speed*direction + self.lateral_speed*left));
+        let center = car.back_origin() + to_rear*car.direction();
+        car.rotate(Rotation::new(self.yaw_rate*dt, center));
+        car.update_trailer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point2, distance_of, vehicle::VehicleClass};
+
+    const DT: f32 = 0.02;
+
+    fn car(steer_angle: f32) -> Car {
+        let mut car = Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.);
+        car.set_steer_angle(steer_angle);
+        car
+    }
+
+    #[test]
+    fn low_speed_matches_kinematic() {
+        for &steer_angle in [0., 2., -5.].iter() {
+            for &speed in [1., -1.5].iter() {
+                let mut dynamic = car(steer_angle);
+                let mut kinematic = car(steer_angle);
+                let mut model = BicycleModel::new(DynamicsConfig::new(TireModel::Linear));
+                for _ in 0..200 {
+                    let heading = dynamic.heading();
+                    model.step(&mut dynamic, speed, DT);
+                    kinematic.forward(speed*DT);
+                    // 记下的横摆角速度就是这一步实际转过的角度
+                    assert!((dynamic.heading() - heading - model.yaw_rate*DT).abs() < 1e-4);
+                }
+                assert!(distance_of(dynamic.back_origin(), kinematic.back_origin()) < 1e-3);
+                assert!((dynamic.heading() - kinematic.heading()).abs() < 1e-4);
+                assert_eq!(model.slip_angle(speed), 0.);
+            }
+        }
+    }
+
+    // 稳态转弯时后轴的侧偏角
+    fn rear_slip(tire: TireModel, speed: f32) -> f32 {
+        let mut car = car(1.);
+        let mut model = BicycleModel::new(DynamicsConfig::new(tire));
+        for _ in 0..300 {
+            model.step(&mut car, speed, DT);
+        }
+        let to_rear = car.wheelbase()*model.config.front_weight;
+        f32::atan((model.lateral_speed - to_rear*model.yaw_rate)/speed)
+    }
+
+    #[test]
+    fn slip_grows_with_speed() {
+        for &tire in TireModel::ALL.iter() {
+            let slips: Vec<_> = [5., 10., 20.].iter().map(|&speed| rear_slip(tire, speed)).collect();
+            // 左转时后轮向外(右)滑
+            assert!(slips[0] < 0.);
+            assert!(slips[1] < slips[0]);
+            assert!(slips[2] < slips[1]);
+        }
+    }
+
+    #[test]
+    fn tire_names() {
+        for &tire in TireModel::ALL.iter() {
+            assert_eq!(TireModel::from_name(tire.name()), Some(tire));
+        }
+        assert_eq!(TireModel::from_name("slick"), None);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{MapKind, Rotation, SPEED, new_rotation_matrix, exam::{ExamEvent, ExamStatus}, geometry::ray_cast, lidar::{Lidar, LidarConfig}, linear_algebra::Vector2D, rng::Rng, sim::Sim, vehicle::VehicleClass};
+
+const RAY_COUNT: usize = 8;
+const RAY_RANGE: f32 = 10.;
+const PASS_REWARD: f32 = 100.;
+// reset时起始位置和朝向的随机扰动
+const START_OFFSET: f32 = 0.3;
+const START_ANGLE: f32 = 3./180.*std::f32::consts::PI;
+
+#[derive(Clone, Copy)]
+pub struct Action {
+    // [-lock_turns, lock_turns], 单位同Car::steer_angle, 向左为正
+    pub steer_angle: f32,
+    // [-SPEED, SPEED], <0 表示倒车
+    pub speed: f32,
+}
+
+#[derive(Clone)]
+pub struct Observation {
+    // 后轴中点
+    pub x: f32,
+    pub y: f32,
+    pub heading: f32,
+    pub speed: f32,
+    pub steer_angle: f32,
+    // 从车身中心沿RAY_COUNT个方向(从车头开始逆时针)到最近边线的距离
+    pub line_distances: [f32; RAY_COUNT],
+    // 倒车雷达各探头的距离
+    pub ultrasonic: Vec<f32>,
+    // 激光雷达一圈的测距, 没有启用激光雷达时为空
+    pub lidar: Vec<f32>,
+}
+
+impl Observation {
+    pub fn to_vec(&self) -> Vec<f32> {
+        let mut v = vec![self.x, self.y, self.heading, self.speed, self.steer_angle];
+        v.extend_from_slice(&self.line_distances);
+        v.extend_from_slice(&self.ultrasonic);
+        v.extend_from_slice(&self.lidar);
+        v
+    }
+}
+
+pub struct StepInfo {
+    pub events: Vec<ExamEvent>,
+    pub score: i32,
+    pub time: f32,
+    // 因超时而结束
+    pub truncated: bool,
+}
+
+// gym风格的强化学习环境, 不创建窗口也不绘图
+pub struct Env {
+    sim: Sim,
+    rng: Rng,
+    // 每步仿真的时长(s)
+    pub dt: f32,
+    pub max_time: f32,
+    pub vehicle: VehicleClass,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Env::new()
+    }
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env {
+            sim: Sim::new(MapKind::BackParking.build(VehicleClass::Car.spec())),
+            rng: Rng::new(0),
+            dt: 0.1,
+            max_time: 120.,
+            vehicle: VehicleClass::Car,
+        }
+    }
+
+    pub fn set_lidar(&mut self, config: Option<LidarConfig>) {
+        self.sim.lidar = config.map(|config| Lidar::new(config, 0));
+    }
+
+    pub fn reset(&mut self, map: MapKind, seed: u64) -> Observation {
+        self.rng = Rng::new(seed);
+        if let Some(lidar) = self.sim.lidar.as_mut() {
+            *lidar = Lidar::new(lidar.config, seed);
+        }
+        self.sim.load(map.build(self.vehicle.spec()));
+        let car = &mut self.sim.car;
+        let offset = self.rng.range(-START_OFFSET, START_OFFSET);
+        let angle = self.rng.range(-START_ANGLE, START_ANGLE);
+        car.translate(new_rotation_matrix(car.heading()) * Vector2D::new_from_x_and_y(offset, 0.));
+        car.rotate(Rotation::new(angle, car.back_origin()));
+        self.sim.update_sensors(None);
+        self.observe()
+    }
+
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, StepInfo) {
+        let sim = &mut self.sim;
+        sim.car.set_steer_angle(action.steer_angle);
+        sim.speed = action.speed.clamp(-SPEED, SPEED);
+        let score = sim.exam.score();
+        let events = sim.step(self.dt);
+        let mut reward = (sim.exam.score() - score) as f32;
+        if sim.exam.status() == ExamStatus::Passed {
+            reward += PASS_REWARD;
+        }
+        let truncated = sim.time >= self.max_time;
+        let done = sim.exam.status() != ExamStatus::Running || truncated;
+        let info = StepInfo {
+            events,
+            score: sim.exam.score(),
+            time: sim.time,
+            truncated,
+        };
+        (self.observe(), reward, done, info)
+    }
+
+    fn observe(&self) -> Observation {
+        let car = &self.sim.car;
+        let lines = self.sim.map.lines();
+        let origin = car.back_origin();
+        let center = car.body.origin;
+        let mut line_distances = [0.; RAY_COUNT];
+        for (i, d) in line_distances.iter_mut().enumerate() {
+            let angle = 2.*std::f32::consts::PI*(i as f32)/(RAY_COUNT as f32);
+            let direction = new_rotation_matrix(angle) * car.direction();
+            *d = ray_cast(center, direction, &lines, RAY_RANGE);
+        }
+        Observation {
+            x: origin.x,
+            y: origin.y,
+            heading: car.heading(),
+            speed: self.sim.speed,
+            steer_angle: car.steer_angle,
+            line_distances,
+            ultrasonic: self.sim.ultrasonic.distances(),
+            lidar: self.sim.lidar.as_ref().map_or(vec![], |lidar| lidar.scan().to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Env, PASS_REWARD};
+    use crate::{MapKind, Rotation, SPEED, exam::ExamEvent, rng::Rng};
+
+    #[test]
+    fn reset_is_deterministic() {
+        let mut env = Env::new();
+        let first = env.reset(MapKind::BackParking, 7).to_vec();
+        env.step(Action { steer_angle: 1., speed: SPEED });
+        assert_eq!(env.reset(MapKind::BackParking, 7).to_vec(), first);
+        assert_ne!(env.reset(MapKind::BackParking, 8).to_vec(), first);
+    }
+
+    #[test]
+    fn failed_step() {
+        let mut env = Env::new();
+        env.reset(MapKind::RightAngleTurn, 0);
+        // 直着往前开, 迟早压线或撞上拐角
+        for _ in 0..1000 {
+            let (_, reward, done, info) = env.step(Action { steer_angle: 0., speed: SPEED });
+            let deduction: i32 = info.events.iter().map(ExamEvent::deduction).sum();
+            assert_eq!(reward, -deduction as f32);
+            if done {
+                assert!(info.events.contains(&ExamEvent::LineTouched) || info.events.contains(&ExamEvent::Collision));
+                assert!(!info.truncated);
+                return;
+            }
+        }
+        panic!("never finished");
+    }
+
+    #[test]
+    fn parked_step() {
+        let mut env = Env::new();
+        env.reset(MapKind::RightAngleTurn, 0);
+        // 直接把车横着摆到终点区域里, 停着就算合格
+        let goal = env.sim.map.goal();
+        let car = &mut env.sim.car;
+        car.rotate(Rotation::new(-std::f32::consts::PI/2. - car.heading(), car.body.origin));
+        car.translate(goal.origin - car.body.origin);
+        let (_, reward, done, info) = env.step(Action { steer_angle: 0., speed: 0. });
+        assert_eq!(info.events, vec![ExamEvent::Parked]);
+        assert_eq!(reward, PASS_REWARD);
+        assert!(done);
+    }
+
+    #[test]
+    fn step_is_deterministic() {
+        let run = || {
+            let mut env = Env::new();
+            let mut rng = Rng::new(1);
+            let mut trace = vec![env.reset(MapKind::BackParking, 3).to_vec()];
+            for i in 0..200 {
+                let (observation, reward, done, info) = env.step(Action { steer_angle: rng.range(-1., 1.), speed: rng.range(-SPEED, SPEED) });
+                assert!((info.time - (i + 1) as f32*env.dt).abs() < 1e-3);
+                trace.push(observation.to_vec());
+                trace.push(vec![reward, done as i32 as f32]);
+                if done {
+                    break;
+                }
+            }
+            trace
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn observe_clamps_speed() {
+        let mut env = Env::new();
+        let start = env.reset(MapKind::ParallelParking, 0);
+        let (observation, ..) = env.step(Action { steer_angle: 0., speed: 10.*SPEED });
+        assert_eq!(observation.speed, SPEED);
+        // 车往前开了, 车头方向不变
+        assert!(((observation.x - start.x).powi(2) + (observation.y - start.y).powi(2)).sqrt() > 0.);
+        assert_eq!(observation.heading, start.heading);
+        assert_eq!(observation.line_distances.len(), start.line_distances.len());
+    }
+}
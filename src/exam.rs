@@ -0,0 +1,267 @@
+use crate::{Car, Map, Rect, obstacle::Obstacle};
+
+pub const FULL_SCORE: i32 = 100;
+pub const PASS_SCORE: i32 = 80;
+// 停车超过这么久(s)再往同一个方向走算中途停车, 换挡倒车前的停车不算
+const STOP_TIME: f32 = 2.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExamStatus {
+    Running,
+    Passed,
+    Failed,
+}
+
+impl ExamStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExamStatus::Running => "running",
+            ExamStatus::Passed => "passed",
+            ExamStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExamEvent {
+    // 车轮压线
+    LineTouched,
+    // 车身碰到障碍物
+    Collision,
+    // 挂车折叠
+    Jackknife,
+    // 停在目标区域内
+    Parked,
+    // 超过项目的限时
+    Timeout,
+    // 中途停车
+    Stopped,
+}
+
+impl ExamEvent {
+    pub const ALL: [ExamEvent; 6] = [
+        ExamEvent::LineTouched, ExamEvent::Collision, ExamEvent::Jackknife, ExamEvent::Parked, ExamEvent::Timeout,
+        ExamEvent::Stopped,
+    ];
+
+    pub fn deduction(&self) -> i32 {
+        match self {
+            ExamEvent::LineTouched => 100,
+            ExamEvent::Collision => 100,
+            ExamEvent::Jackknife => 100,
+            ExamEvent::Parked => 0,
+            ExamEvent::Timeout => 100,
+            ExamEvent::Stopped => 5,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExamEvent::LineTouched => "line_touched",
+            ExamEvent::Collision => "collision",
+            ExamEvent::Jackknife => "jackknife",
+            ExamEvent::Parked => "parked",
+            ExamEvent::Timeout => "timeout",
+            ExamEvent::Stopped => "stopped",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ExamEvent> {
+        ExamEvent::ALL.iter().cloned().find(|event| event.name() == name)
+    }
+}
+
+pub struct Exam {
+    score: i32,
+    status: ExamStatus,
+    // 压线和碰撞只在刚发生时扣分
+    touching: bool,
+    colliding: bool,
+    jackknifed: bool,
+    // 最近一次行驶的方向, 1前进, -1倒车, 还没动过为0
+    direction: f32,
+    // 已经停了多久(s)
+    stopped: f32,
+}
+
+impl Default for Exam {
+    fn default() -> Self {
+        Exam::new()
+    }
+}
+
+impl Exam {
+    pub fn new() -> Self {
+        Exam {
+            score: FULL_SCORE,
+            status: ExamStatus::Running,
+            touching: false,
+            colliding: false,
+            jackknifed: false,
+            direction: 0.,
+            stopped: 0.,
+        }
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    pub fn status(&self) -> ExamStatus {
+        self.status
+    }
+
+    // 返回这一次新产生的事件, obstacles包括地图上的障碍物和移动的交通参与者, dt是距上次更新的时间
+    pub fn update(&mut self, car: &Car, map: &dyn Map, obstacles: &[Obstacle], speed: f32, dt: f32) -> Vec<ExamEvent> {
+        let mut events = vec![];
+        if self.status != ExamStatus::Running {
+            return events;
+        }
+        let lines = map.lines();
+        let mut wheels: Vec<Rect> = car.wheels().iter().map(|wheel| **wheel).collect();
+        let mut bodies = vec![car.body];
+        if let Some(trailer) = &car.trailer {
+            wheels.extend_from_slice(&trailer.wheels());
+            bodies.push(trailer.body());
+        }
+        let touching = wheels.iter()
+            .any(|wheel| wheel.edges().iter().any(|edge| lines.iter().any(|line| line.intersects(edge))));
+        if touching && !self.touching {
+            events.push(ExamEvent::LineTouched);
+        }
+        self.touching = touching;
+        let colliding = obstacles.iter().any(|obstacle| bodies.iter().any(|body| obstacle.collides(body)));
+        if colliding && !self.colliding {
+            events.push(ExamEvent::Collision);
+        }
+        self.colliding = colliding;
+        let jackknifed = car.trailer.is_some_and(|trailer| trailer.jackknifed);
+        if jackknifed && !self.jackknifed {
+            events.push(ExamEvent::Jackknife);
+        }
+        self.jackknifed = jackknifed;
+        if speed == 0. {
+            self.stopped += dt;
+        } else {
+            if self.stopped > STOP_TIME && speed.signum() == self.direction {
+                events.push(ExamEvent::Stopped);
+            }
+            self.stopped = 0.;
+            self.direction = speed.signum();
+        }
+        let goal = map.goal();
+        let body = &car.body;
+        if speed == 0. && [body.lt(), body.rt(), body.lb(), body.rb()].iter().all(|p| goal.contains(*p)) {
+            events.push(ExamEvent::Parked);
+        }
+        for event in events.iter() {
+            self.record(*event);
+        }
+        events
+    }
+
+    // 考试模式下到了限时还没完成, 判超时
+    pub fn time_out(&mut self) -> Option<ExamEvent> {
+        if self.status != ExamStatus::Running {
+            return None;
+        }
+        self.record(ExamEvent::Timeout);
+        Some(ExamEvent::Timeout)
+    }
+
+    fn record(&mut self, event: ExamEvent) {
+        self.score -= event.deduction();
+        if self.score < PASS_SCORE {
+            self.status = ExamStatus::Failed;
+        } else if event == ExamEvent::Parked {
+            self.status = ExamStatus::Passed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Exam, ExamEvent, ExamStatus, FULL_SCORE, PASS_SCORE, STOP_TIME};
+    use crate::{MapKind, Rotation, sim::Sim, vehicle::VehicleClass};
+
+    const DT: f32 = 0.1;
+
+    fn sim() -> Sim {
+        Sim::new(MapKind::RightAngleTurn.build(VehicleClass::Car.spec()))
+    }
+
+    // 以speed开time秒, 返回这段时间的考试事件
+    fn run(sim: &mut Sim, speed: f32, time: f32) -> Vec<ExamEvent> {
+        sim.speed = speed;
+        (0..(time/DT).round() as usize).flat_map(|_| sim.step(DT)).collect()
+    }
+
+    // 停够了再往前走一下
+    fn stop_and_go(sim: &mut Sim) -> Vec<ExamEvent> {
+        let mut events = run(sim, 0., STOP_TIME + 0.5);
+        events.extend(run(sim, 2., 0.2));
+        events
+    }
+
+    // 把车横着摆到终点区域里停下
+    fn park(sim: &mut Sim) -> Vec<ExamEvent> {
+        let goal = sim.map.goal();
+        let car = &mut sim.car;
+        car.rotate(Rotation::new(-std::f32::consts::PI/2. - car.heading(), car.body.origin));
+        car.translate(goal.origin - car.body.origin);
+        run(sim, 0., DT)
+    }
+
+    #[test]
+    fn stopped_midway() {
+        let mut sim = sim();
+        // 还没动过时停多久都不算
+        assert!(run(&mut sim, 0., 5.).is_empty());
+        assert!(run(&mut sim, 2., 0.5).is_empty());
+        // 停得不够久不算
+        assert!(run(&mut sim, 0., STOP_TIME - 0.5).is_empty());
+        assert!(run(&mut sim, 2., 0.2).is_empty());
+        assert_eq!(stop_and_go(&mut sim), vec![ExamEvent::Stopped]);
+        assert_eq!(sim.exam.score(), FULL_SCORE - 5);
+        // 停车换挡倒车不算
+        assert!(run(&mut sim, 0., STOP_TIME + 0.5).is_empty());
+        assert!(run(&mut sim, -2., 0.2).is_empty());
+        assert_eq!(sim.exam.score(), FULL_SCORE - 5);
+        assert_eq!(sim.exam.status(), ExamStatus::Running);
+    }
+
+    #[test]
+    fn pass_and_fail_by_score() {
+        let mut sim = sim();
+        run(&mut sim, 2., 0.5);
+        for _ in 0..2 {
+            stop_and_go(&mut sim);
+        }
+        assert_eq!(park(&mut sim), vec![ExamEvent::Parked]);
+        assert_eq!(sim.exam.score(), FULL_SCORE - 10);
+        assert_eq!(sim.exam.status(), ExamStatus::Passed);
+        // 考完以后不再扣分
+        assert!(stop_and_go(&mut sim).is_empty());
+        assert_eq!(sim.exam.score(), FULL_SCORE - 10);
+
+        let mut sim = self::sim();
+        run(&mut sim, 2., 0.5);
+        let mut stops = 0;
+        while sim.exam.status() == ExamStatus::Running {
+            stop_and_go(&mut sim);
+            stops += 1;
+        }
+        // 扣到合格线以下就不合格
+        assert_eq!(stops, (FULL_SCORE - PASS_SCORE)/5 + 1);
+        assert_eq!(sim.exam.status(), ExamStatus::Failed);
+    }
+
+    #[test]
+    fn time_out_once() {
+        let mut exam = Exam::new();
+        assert_eq!(exam.time_out(), Some(ExamEvent::Timeout));
+        assert_eq!(exam.status(), ExamStatus::Failed);
+        assert_eq!(exam.score(), FULL_SCORE - ExamEvent::Timeout.deduction());
+        assert_eq!(exam.time_out(), None);
+    }
+}
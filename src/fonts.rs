@@ -0,0 +1,95 @@
+use std::borrow::Cow;
+
+use font_kit::{family_name::FamilyName, font::Font, properties::Properties, source::SystemSource};
+
+use car_simulation::json::Value;
+
+// 没有配置时按顺序找的字体, 覆盖Windows、macOS和常见Linux发行版自带的中文字体
+const FALLBACK_FAMILIES: [&str; 10] = [
+    "DengXian", "Microsoft YaHei", "SimHei",
+    "PingFang SC", "Hiragino Sans GB",
+    "Noto Sans CJK SC", "Noto Sans SC", "Source Han Sans SC", "WenQuanYi Micro Hei", "Droid Sans Fallback",
+];
+// 字体里有这几个字才算能显示中文, raqote遇到字体里没有的字会panic
+const PROBE: &str = "倒车入库方向盘";
+// 找不到中文字体时显示英文界面用的字体
+const LATIN_FAMILIES: [&str; 5] = ["Segoe UI", "Helvetica Neue", "DejaVu Sans", "Liberation Sans", "Noto Sans"];
+// 英文界面里用到的ASCII以外的字符
+const LATIN_PROBE: &str = "Reverse 90° Turn→×";
+
+// 用embedded-font特性编译时内嵌的字体, 文件放在res/fonts/fallback.ttf
+#[cfg(feature = "embedded-font")]
+fn embedded() -> Option<Font> {
+    let bytes = include_bytes!("../res/fonts/fallback.ttf");
+    Font::from_bytes(std::sync::Arc::new(bytes.to_vec()), 0).ok()
+}
+
+#[cfg(not(feature = "embedded-font"))]
+fn embedded() -> Option<Font> {
+    None
+}
+
+fn covers(font: &Font, probe: &str) -> bool {
+    probe.chars().all(|c| font.glyph_for_char(c).is_some())
+}
+
+fn covers_cjk(font: &Font) -> bool {
+    covers(font, PROBE)
+}
+
+fn from_family(source: &SystemSource, family: &str) -> Option<Font> {
+    let handle = source.select_best_match(&[FamilyName::Title(family.to_string())], &Properties::new()).ok()?;
+    // 找不到时系统可能会返回别的字体代替, 要再检查一遍
+    handle.load().ok().filter(covers_cjk)
+}
+
+// 配置文件的"font"部分: {"path": "/path/to/font.ttf", "families": ["Noto Sans CJK SC"]}
+// 依次尝试: 配置的字体文件, 配置的字体族, 内置的字体族列表, 内嵌字体, 都没有时返回None
+pub fn load(config: Option<&Value>) -> Option<Font> {
+    if let Some(path) = config.and_then(|config| config.get("path")).and_then(Value::as_str) {
+        match Font::from_path(path, 0) {
+            Ok(font) if covers_cjk(&font) => return Some(font),
+            Ok(_) => println!("font {} has no Chinese glyphs, ignored", path),
+            Err(err) => println!("cannot load font {}: {:?}", path, err),
+        }
+    }
+    let configured: Vec<&str> = config.and_then(|config| config.get("families")).and_then(Value::as_array)
+        .map_or(vec![], |families| families.iter().filter_map(Value::as_str).collect());
+    let source = SystemSource::new();
+    let font = configured.iter().chain(FALLBACK_FAMILIES.iter())
+        .find_map(|family| from_family(&source, family))
+        .or_else(embedded);
+    match &font {
+        Some(font) => println!("using font {}", font.full_name()),
+        None => println!("no font with Chinese glyphs found, showing English labels; install one of {:?} or set \"font\" in {}",
+            FALLBACK_FAMILIES, crate::CONFIG_PATH),
+    }
+    font
+}
+
+// 没有中文字体时显示英文界面的字体, 先找带箭头等符号的, 再退到系统默认的无衬线字体和内嵌字体
+// 一个字体都没有时返回None, 由调用方提示后退出
+pub fn load_latin() -> Option<Font> {
+    let source = SystemSource::new();
+    let load = |family: FamilyName| source.select_best_match(&[family], &Properties::new()).ok()?.load().ok();
+    let font = LATIN_FAMILIES.iter()
+        .filter_map(|family| load(FamilyName::Title(family.to_string())))
+        .find(|font| covers(font, LATIN_PROBE))
+        .or_else(|| load(FamilyName::SansSerif))
+        .or_else(embedded);
+    match &font {
+        Some(font) => println!("using font {}", font.full_name()),
+        None => println!("no usable font found, install a TrueType font such as one of {:?} or build with --features embedded-font",
+            LATIN_FAMILIES),
+    }
+    font
+}
+
+// 字体里没有的字换成'?', 命令行和配置文件里给的学员名字可能是英文字体显示不了的
+pub fn printable<'a>(font: &Font, text: &'a str) -> Cow<'a, str> {
+    if text.chars().all(|c| c.is_whitespace() || font.glyph_for_char(c).is_some()) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.chars().map(|c| if c.is_whitespace() || font.glyph_for_char(c).is_some() {c} else {'?'}).collect())
+    }
+}
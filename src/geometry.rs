@@ -0,0 +1,90 @@
+use crate::{Point, distance_of, linear_algebra::Vector2D};
+
+#[derive(Clone, Copy)]
+pub struct Segment {
+    pub a: Point,
+    pub b: Point,
+}
+
+impl Segment {
+    pub fn new(a: Point, b: Point) -> Self {
+        Segment { a, b }
+    }
+
+    // 线段上离p最近的点
+    pub fn closest_point(&self, p: Point) -> Point {
+        let ab = self.b - self.a;
+        let len2 = dot(ab, ab);
+        if len2 == 0. {
+            return self.a;
+        }
+        let t = (dot(p - self.a, ab) / len2).clamp(0., 1.);
+        self.a + t * ab
+    }
+
+    pub fn distance_to(&self, p: Point) -> f32 {
+        distance_of(self.closest_point(p), p)
+    }
+
+    pub fn intersects(&self, other: &Segment) -> bool {
+        let r = self.b - self.a;
+        let s = other.b - other.a;
+        let d = cross(r, s);
+        if d == 0. {
+            return false;
+        }
+        let qp = other.a - self.a;
+        let t = cross(qp, s) / d;
+        let u = cross(qp, r) / d;
+        (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)
+    }
+
+    // 从origin沿单位向量direction射出的射线打到线段上的距离
+    pub fn ray_distance(&self, origin: Point, direction: Vector2D) -> Option<f32> {
+        let s = self.b - self.a;
+        let d = cross(direction, s);
+        if d == 0. {
+            return None;
+        }
+        let qp = self.a - origin;
+        let t = cross(qp, s) / d;
+        let u = cross(qp, direction) / d;
+        if t >= 0. && (0. ..=1.).contains(&u) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+// 射线打到的最近线段的距离, 超过max_range时返回max_range
+pub fn ray_cast(origin: Point, direction: Vector2D, segments: &[Segment], max_range: f32) -> f32 {
+    segments.iter()
+        .filter_map(|s| s.ray_distance(origin, direction))
+        .fold(max_range, f32::min)
+}
+
+pub fn dot(a: Vector2D, b: Vector2D) -> f32 {
+    a.x()*b.x() + a.y()*b.y()
+}
+
+pub fn cross(a: Vector2D, b: Vector2D) -> f32 {
+    a.x()*b.y() - a.y()*b.x()
+}
+
+// 向量相对x轴的角度
+pub fn angle_of(v: Vector2D) -> f32 {
+    f32::atan2(v.y(), v.x())
+}
+
+// 把角度规范到(-PI, PI]
+pub fn normalize_angle(angle: f32) -> f32 {
+    let pi = std::f32::consts::PI;
+    let mut a = angle % (2.*pi);
+    if a > pi {
+        a -= 2.*pi;
+    } else if a <= -pi {
+        a += 2.*pi;
+    }
+    a
+}
@@ -0,0 +1,195 @@
+use font_kit::font::Font;
+use raqote::{DrawTarget, SolidSource, Source, DrawOptions, PathBuilder, StrokeStyle};
+
+use car_simulation::{Car, Point, Rect, View, point2, SCALE, geometry::normalize_angle, sim::Gear, steering::{SteeringActuator, wheel_turns},
+    ultrasonic::{Ultrasonic, Zone}};
+
+use crate::{real2pixel, pixel2real, fonts, i18n::{self, Message}, render::{Painter, solid as solid_color}};
+
+const WHEEL_RADIUS: f32 = 45.;
+const GAUGE_RADIUS: f32 = 40.;
+// 速度表满量程(km/h)和指针扫过的角度
+const GAUGE_MAX: f32 = 50.;
+const GAUGE_SWEEP: f32 = 270./180.*std::f32::consts::PI;
+const GEARS: [Gear; 3] = [Gear::Reverse, Gear::Neutral, Gear::Drive];
+// 倒车雷达指示灯、速度表和档位相对方向盘中心的竖直位置(像素)
+const INDICATOR_Y: f32 = -75.;
+const SPEEDOMETER_Y: f32 = 150.;
+const GEAR_Y: f32 = 215.;
+// 整个仪表从指示灯上边到档位文字下边, 在方向盘中心上下各占的高度和左右各占的宽度(像素)
+pub const TOP: f32 = -INDICATOR_Y + 15.;
+pub const BOTTOM: f32 = GEAR_Y + 5.;
+pub const HALF_WIDTH: f32 = 75.;
+
+fn solid(r: u8, g: u8, b: u8) -> Source<'static> {
+    Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, r, g, b))
+}
+
+// 像素坐标下, 从正上方顺时针转angle、距离radius的点
+fn polar(cx: f32, cy: f32, radius: f32, angle: f32) -> (f32, f32) {
+    (cx + radius*angle.sin(), cy - radius*angle.cos())
+}
+
+// 驾校教方向盘按圈数打, 显示成"左1.5圈", turns是方向盘实际转过的圈数
+pub fn turns_text(turns: f32) -> String {
+    if turns.abs() < 0.05 {
+        i18n::tr(Message::SteeringCentered).to_string()
+    } else {
+        let message = if turns > 0. {Message::SteeringLeft} else {Message::SteeringRight};
+        i18n::format(message, &[&format!("{:.1}", turns.abs())])
+    }
+}
+
+// 菜单栏里的倒车雷达蜂鸣指示灯, 按蜂鸣间隔闪烁
+pub fn draw_sonar_indicator(dt: &mut DrawTarget, font: &Font, ultrasonic: &Ultrasonic, origin: Point, time: f32) {
+    let zone = ultrasonic.zone();
+    let on = match zone.beep_interval() {
+        None => false,
+        Some(0.) => true,
+        Some(interval) => time % (interval*2.) < interval,
+    };
+    let color = if on {zone.color()} else {Zone::Clear.color()};
+    let light = Rect::new(origin, 100./SCALE, 30./SCALE, Some(color));
+    light.draw(&mut Painter::new(dt), (0., 0.).into());
+    let distance = ultrasonic.distances().into_iter().fold(f32::INFINITY, f32::min);
+    let text = if zone == Zone::Clear {"--".to_string()} else {format!("{:.2}m", distance)};
+    let lb = light.lb();
+    let (x, y) = real2pixel(point2(lb.x+0.3, lb.y+0.25)).into();
+    dt.draw_text(font, 20.0, &text, raqote::Point::new(x, y),
+        &Source::Solid(solid_color(car_simulation::Color::rgb(0, 0, 0))), &DrawOptions::new());
+}
+
+// 用鼠标拖着方向盘转: 记住上一帧指针的角度, 指针转过多少方向盘就转多少
+pub struct WheelDrag {
+    angle: f32,
+}
+
+// 菜单栏下方的仪表: 倒车雷达指示灯、方向盘和圈数、速度表、档位
+pub struct Hud<'a> {
+    font: &'a Font,
+    // 方向盘中心, 指示灯画在上面, 速度表和档位依次画在下面
+    origin: Point,
+    // 盖在地图上时衬一层半透明底色
+    backdrop: bool,
+}
+
+impl<'a> Hud<'a> {
+    pub fn new(origin: Point, backdrop: bool, font: &'a Font) -> Self {
+        Hud { font, origin, backdrop }
+    }
+
+    // 窗口大小变了以后重新摆放
+    pub fn set_position(&mut self, origin: Point, backdrop: bool) {
+        self.origin = origin;
+        self.backdrop = backdrop;
+    }
+
+    // 倒车雷达指示灯的中心
+    pub fn indicator(&self) -> Point {
+        let (cx, cy) = real2pixel(self.origin).into();
+        pixel2real(point2(cx, cy + INDICATOR_Y))
+    }
+
+    // 指针相对方向盘中心的角度, 从正上方顺时针
+    fn pointer_angle(&self, pixel: Point) -> f32 {
+        let (cx, cy) = real2pixel(self.origin).into();
+        f32::atan2(pixel.x - cx, cy - pixel.y)
+    }
+
+    // 在方向盘上按下鼠标时开始拖动, 离中心太近时角度不稳定, 不算
+    pub fn grab(&self, pixel: Point) -> Option<WheelDrag> {
+        let (cx, cy) = real2pixel(self.origin).into();
+        let distance = f32::hypot(pixel.x - cx, pixel.y - cy);
+        if distance > 10. && distance < WHEEL_RADIUS + 10. {
+            Some(WheelDrag { angle: self.pointer_angle(pixel) })
+        } else {
+            None
+        }
+    }
+
+    // 返回这一帧方向盘转过的圈数, 向左为正
+    pub fn drag(&self, drag: &mut WheelDrag, pixel: Point) -> f32 {
+        let angle = self.pointer_angle(pixel);
+        let delta = normalize_angle(angle - drag.angle);
+        drag.angle = angle;
+        -delta/(2.*std::f32::consts::PI)
+    }
+
+    pub fn draw(&self, dt: &mut DrawTarget, car: &Car, speed: f32, steering: Option<&SteeringActuator>) {
+        let (cx, cy) = real2pixel(self.origin).into();
+        if self.backdrop {
+            dt.fill_rect(cx - HALF_WIDTH, cy - TOP, 2.*HALF_WIDTH, TOP + BOTTOM,
+                &Source::Solid(SolidSource::from_unpremultiplied_argb(0xc0, 0x00, 0x00, 0x00)), &DrawOptions::new());
+        }
+        self.draw_wheel(dt, cx, cy, car, steering);
+        self.draw_speedometer(dt, cx, cy + SPEEDOMETER_Y, speed);
+        self.draw_gear(dt, cx, cy + GEAR_Y, Gear::of(speed));
+    }
+
+    // 方向盘转一圈画面上也转一圈, 向左转时逆时针转
+    fn draw_wheel(&self, dt: &mut DrawTarget, cx: f32, cy: f32, car: &Car, steering: Option<&SteeringActuator>) {
+        let rotation = -wheel_turns(car.steer_angle, car.spec.lock_turns)*2.*std::f32::consts::PI;
+        let grey = solid(0x50, 0x50, 0x50);
+        let mut pb = PathBuilder::new();
+        pb.arc(cx, cy, WHEEL_RADIUS, 0., 2.*std::f32::consts::PI);
+        dt.stroke(&pb.finish(), &grey, &StrokeStyle { width: 8., ..StrokeStyle::default() }, &DrawOptions::new());
+        let spoke_style = StrokeStyle { width: 6., ..StrokeStyle::default() };
+        for spoke in [-std::f32::consts::PI/2., std::f32::consts::PI/2., std::f32::consts::PI].iter() {
+            let mut pb = PathBuilder::new();
+            pb.move_to(cx, cy);
+            let (x, y) = polar(cx, cy, WHEEL_RADIUS, rotation + spoke);
+            pb.line_to(x, y);
+            dt.stroke(&pb.finish(), &grey, &spoke_style, &DrawOptions::new());
+        }
+        let mut pb = PathBuilder::new();
+        pb.arc(cx, cy, 12., 0., 2.*std::f32::consts::PI);
+        dt.fill(&pb.finish(), &grey, &DrawOptions::new());
+        // 方向盘正上方的标记, 看它转到哪就知道打了几圈
+        let mut pb = PathBuilder::new();
+        let (x, y) = polar(cx, cy, WHEEL_RADIUS, rotation);
+        pb.arc(x, y, 6., 0., 2.*std::f32::consts::PI);
+        dt.fill(&pb.finish(), &solid(0xd0, 0x40, 0x40), &DrawOptions::new());
+        let turns = |angle: f32| wheel_turns(angle, car.spec.lock_turns);
+        let mut text = turns_text(turns(car.steer_angle));
+        if let Some(steering) = steering {
+            if (turns(steering.target) - turns(car.steer_angle)).abs() >= 0.05 {
+                text = format!("{}→{}", text, turns_text(turns(steering.target)));
+            }
+        }
+        dt.draw_text(self.font, 18., &fonts::printable(self.font, &text), (cx - 50., cy + WHEEL_RADIUS + 28.).into(),
+            &solid(0xff, 0xff, 0xff), &DrawOptions::new());
+    }
+
+    fn draw_speedometer(&self, dt: &mut DrawTarget, cx: f32, cy: f32, speed: f32) {
+        let kmh = speed.abs()*3.6;
+        let start = -GAUGE_SWEEP/2.;
+        let mut pb = PathBuilder::new();
+        for i in 0..=30 {
+            let (x, y) = polar(cx, cy, GAUGE_RADIUS, start + GAUGE_SWEEP*(i as f32)/30.);
+            if i == 0 {
+                pb.move_to(x, y);
+            } else {
+                pb.line_to(x, y);
+            }
+        }
+        dt.stroke(&pb.finish(), &solid(0xa0, 0xa0, 0xa0), &StrokeStyle { width: 4., ..StrokeStyle::default() },
+            &DrawOptions::new());
+        let mut pb = PathBuilder::new();
+        pb.move_to(cx, cy);
+        let (x, y) = polar(cx, cy, GAUGE_RADIUS - 6., start + GAUGE_SWEEP*(kmh/GAUGE_MAX).min(1.));
+        pb.line_to(x, y);
+        dt.stroke(&pb.finish(), &solid(0xd0, 0x40, 0x40), &StrokeStyle { width: 3., ..StrokeStyle::default() },
+            &DrawOptions::new());
+        dt.draw_text(self.font, 16., &format!("{:.0}km/h", kmh), (cx - 25., cy + GAUGE_RADIUS).into(),
+            &solid(0xff, 0xff, 0xff), &DrawOptions::new());
+    }
+
+    // R N D排成一行, 当前档位高亮
+    fn draw_gear(&self, dt: &mut DrawTarget, cx: f32, cy: f32, gear: Gear) {
+        for (i, g) in GEARS.iter().enumerate() {
+            let source = if *g == gear {solid(0x66, 0xfc, 0x03)} else {solid(0x60, 0x60, 0x60)};
+            dt.draw_text(self.font, 24., g.name(), (cx - 40. + (i as f32)*30., cy).into(),
+                &source, &DrawOptions::new());
+        }
+    }
+}
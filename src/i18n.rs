@@ -0,0 +1,328 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use car_simulation::{MapKind, exam::ExamEvent};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    Chinese,
+    English,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Chinese, Language::English];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Chinese => "zh",
+            Language::English => "en",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Language> {
+        Language::ALL.iter().cloned().find(|language| language.name() == name)
+    }
+
+    // 菜单里的语言切换按钮依次切换
+    pub fn next(self) -> Language {
+        let i = Language::ALL.iter().position(|&language| language == self).unwrap();
+        Language::ALL[(i + 1) % Language::ALL.len()]
+    }
+}
+
+// 界面上显示给用户的文字, 带{}的在显示时替换成数字
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Message {
+    BackParking,
+    ParallelParking,
+    RightAngleTurn,
+    Crossing,
+    // 语言切换按钮上显示的当前语言
+    LanguageName,
+    SteeringCentered,
+    SteeringLeft,
+    SteeringRight,
+    LineTouched,
+    Collision,
+    Jackknife,
+    Parked,
+    // 考试事件: 事件, 扣分, 总分
+    ExamEventLog,
+    Score,
+    Ultrasonic,
+    SurroundView,
+    RearSteer,
+    SelfAlign,
+    SteeringRate,
+    Reset,
+    Settings,
+    Speed,
+    LockTurns,
+    TurningRadius,
+    TrackWidth,
+    FrontOverhang,
+    RearOverhang,
+    // 每米的像素数
+    Scale,
+    Defaults,
+    Timeout,
+    Stopped,
+    ExamMode,
+    // 开考播报: 项目, 限时
+    ExamAnnounce,
+    // 剩余秒数
+    TimeLeft,
+    // 扣分记录: 时间, 事件, 扣分
+    DeductionEntry,
+    Passed,
+    Failed,
+    // 每项结果: 项目, 合格/不合格, 用时
+    ItemResult,
+    // 总成绩: 合格/不合格, 得分
+    ExamResult,
+    Stats,
+    // 学员名
+    NoAttempts,
+    // 学员名, 练习次数, 合格率
+    StatsSummary,
+    // 项目, 练习次数, 合格率
+    MapStats,
+    FrequentMistakes,
+    PassRateByDay,
+    StartOccupied,
+    // 横向误差, 航向误差
+    AutopilotFinished,
+    // 开关前后的最小转弯半径
+    RearSteerOn,
+    RearSteerOff,
+    // 学员名
+    PracticeSaved,
+    // 错误
+    ExamConfigInvalid,
+    // 文件路径, 错误
+    StatsFileInvalid,
+    // 改名后的路径
+    StatsFileMoved,
+    // 错误
+    StatsFileStuck,
+    // 文件路径, 错误
+    PracticeSaveFailed,
+    // 学员名
+    TraineeSelected,
+    FullscreenSize,
+}
+
+impl Message {
+    // 只在测试里用来检查每种语言都有翻译
+    #[cfg(test)]
+    pub const ALL: [Message; 57] = [
+        Message::BackParking, Message::ParallelParking, Message::RightAngleTurn, Message::Crossing,
+        Message::LanguageName, Message::SteeringCentered, Message::SteeringLeft, Message::SteeringRight,
+        Message::LineTouched, Message::Collision, Message::Jackknife, Message::Parked, Message::ExamEventLog,
+        Message::Score, Message::Ultrasonic, Message::SurroundView, Message::RearSteer, Message::SelfAlign,
+        Message::SteeringRate, Message::Reset, Message::Settings, Message::Speed, Message::LockTurns,
+        Message::TurningRadius, Message::TrackWidth, Message::FrontOverhang, Message::RearOverhang,
+        Message::Scale, Message::Defaults, Message::Timeout, Message::Stopped, Message::ExamMode,
+        Message::ExamAnnounce, Message::TimeLeft, Message::DeductionEntry, Message::Passed, Message::Failed,
+        Message::ItemResult, Message::ExamResult, Message::Stats, Message::NoAttempts, Message::StatsSummary,
+        Message::MapStats, Message::FrequentMistakes, Message::PassRateByDay, Message::StartOccupied,
+        Message::AutopilotFinished, Message::RearSteerOn, Message::RearSteerOff, Message::PracticeSaved,
+        Message::ExamConfigInvalid, Message::StatsFileInvalid, Message::StatsFileMoved, Message::StatsFileStuck,
+        Message::PracticeSaveFailed, Message::TraineeSelected, Message::FullscreenSize,
+    ];
+}
+
+impl From<MapKind> for Message {
+    fn from(kind: MapKind) -> Self {
+        match kind {
+            MapKind::BackParking => Message::BackParking,
+            MapKind::ParallelParking => Message::ParallelParking,
+            MapKind::RightAngleTurn => Message::RightAngleTurn,
+            MapKind::Crossing => Message::Crossing,
+        }
+    }
+}
+
+impl From<ExamEvent> for Message {
+    fn from(event: ExamEvent) -> Self {
+        match event {
+            ExamEvent::LineTouched => Message::LineTouched,
+            ExamEvent::Collision => Message::Collision,
+            ExamEvent::Jackknife => Message::Jackknife,
+            ExamEvent::Parked => Message::Parked,
+            ExamEvent::Timeout => Message::Timeout,
+            ExamEvent::Stopped => Message::Stopped,
+        }
+    }
+}
+
+fn chinese(message: Message) -> &'static str {
+    match message {
+        Message::BackParking => "倒车入库",
+        Message::ParallelParking => "侧方停车",
+        Message::RightAngleTurn => "直角转弯",
+        Message::Crossing => "人行横道",
+        Message::LanguageName => "中文",
+        Message::SteeringCentered => "回正",
+        Message::SteeringLeft => "左{}圈",
+        Message::SteeringRight => "右{}圈",
+        Message::LineTouched => "车轮压线",
+        Message::Collision => "车身碰撞",
+        Message::Jackknife => "挂车折叠",
+        Message::Parked => "停车到位",
+        Message::ExamEventLog => "考试: {} (扣{}分), 得分 {}",
+        Message::Score => "得分 {}",
+        Message::Ultrasonic => "倒车雷达",
+        Message::SurroundView => "全景影像",
+        Message::RearSteer => "后轮转向",
+        Message::SelfAlign => "松手回正",
+        Message::SteeringRate => "转向速度",
+        Message::Reset => "重新开始",
+        Message::Settings => "车辆设置",
+        Message::Speed => "车速",
+        Message::LockTurns => "方向盘圈数",
+        Message::TurningRadius => "转弯半径",
+        Message::TrackWidth => "轮距",
+        Message::FrontOverhang => "前悬",
+        Message::RearOverhang => "后悬",
+        Message::Scale => "比例尺",
+        Message::Defaults => "恢复默认",
+        Message::Timeout => "超时",
+        Message::Stopped => "中途停车",
+        Message::ExamMode => "考试模式",
+        Message::ExamAnnounce => "{}, 限时{}秒, 请开始",
+        Message::TimeLeft => "剩余 {} 秒",
+        Message::DeductionEntry => "{}秒 {} 扣{}分",
+        Message::Passed => "合格",
+        Message::Failed => "不合格",
+        Message::ItemResult => "{}: {}, 用时{}秒",
+        Message::ExamResult => "考试{}, 得分 {}",
+        Message::Stats => "练习统计",
+        Message::NoAttempts => "{}还没有练习记录",
+        Message::StatsSummary => "{}: 练习{}次, 合格率{}%",
+        Message::MapStats => "{}: {}次, 合格率{}%",
+        Message::FrequentMistakes => "    常见错误: {}",
+        Message::PassRateByDay => "每天的合格率",
+        Message::StartOccupied => "起点有车, 先把车开走再添加",
+        Message::AutopilotFinished => "自动驾驶结束: 横向误差 {}m, 航向误差 {}rad",
+        Message::RearSteerOn => "后轮转向开: 最小转弯半径 {}m -> {}m",
+        Message::RearSteerOff => "后轮转向关: 最小转弯半径 {}m -> {}m",
+        Message::PracticeSaved => "已保存{}的练习记录",
+        Message::ExamConfigInvalid => "考试配置无效, 考所有项目: {}",
+        Message::StatsFileInvalid => "练习记录文件{}无效: {}",
+        Message::StatsFileMoved => "已把无效的练习记录文件改名为{}",
+        Message::StatsFileStuck => "无效的练习记录文件改不了名, 这次的练习记录不会保存: {}",
+        Message::PracticeSaveFailed => "无法把练习记录保存到{}: {}",
+        Message::TraineeSelected => "当前学员: {}",
+        Message::FullscreenSize => "--fullscreen需要给出屏幕大小, 例如 --fullscreen 1920x1080",
+    }
+}
+
+fn english(message: Message) -> &'static str {
+    match message {
+        Message::BackParking => "Reverse",
+        Message::ParallelParking => "Parallel",
+        Message::RightAngleTurn => "90° Turn",
+        Message::Crossing => "Crossing",
+        Message::LanguageName => "English",
+        Message::SteeringCentered => "Centre",
+        Message::SteeringLeft => "L {} turns",
+        Message::SteeringRight => "R {} turns",
+        Message::LineTouched => "line touched",
+        Message::Collision => "collision",
+        Message::Jackknife => "jackknifed",
+        Message::Parked => "parked",
+        Message::ExamEventLog => "exam: {} (-{}), score = {}",
+        Message::Score => "Score {}",
+        Message::Ultrasonic => "Sonar",
+        Message::SurroundView => "360 view",
+        Message::RearSteer => "Rear steer",
+        Message::SelfAlign => "Self-centre",
+        Message::SteeringRate => "Steer rate",
+        Message::Reset => "Restart",
+        Message::Settings => "Settings",
+        Message::Speed => "Speed",
+        Message::LockTurns => "Lock turns",
+        Message::TurningRadius => "Turn radius",
+        Message::TrackWidth => "Track",
+        Message::FrontOverhang => "Front overhang",
+        Message::RearOverhang => "Rear overhang",
+        Message::Scale => "Scale",
+        Message::Defaults => "Defaults",
+        Message::Timeout => "time out",
+        Message::Stopped => "stopped midway",
+        Message::ExamMode => "Exam mode",
+        Message::ExamAnnounce => "{}: {} s, begin",
+        Message::TimeLeft => "{} s left",
+        Message::DeductionEntry => "{} s {} -{}",
+        Message::Passed => "passed",
+        Message::Failed => "failed",
+        Message::ItemResult => "{}: {} in {} s",
+        Message::ExamResult => "Exam {}, score {}",
+        Message::Stats => "Statistics",
+        Message::NoAttempts => "No attempts by {} yet",
+        Message::StatsSummary => "{}: {} attempts, {}% passed",
+        Message::MapStats => "{}: {} attempts, {}% passed",
+        Message::FrequentMistakes => "    frequent mistakes: {}",
+        Message::PassRateByDay => "Pass rate by day",
+        Message::StartOccupied => "the start is occupied, drive the car away before adding another one",
+        Message::AutopilotFinished => "autopilot finished: cross track error = {}m, heading error = {}rad",
+        Message::RearSteerOn => "rear steering on: minimum turning radius {}m -> {}m",
+        Message::RearSteerOff => "rear steering off: minimum turning radius {}m -> {}m",
+        Message::PracticeSaved => "practice record saved for {}",
+        Message::ExamConfigInvalid => "invalid exam config, using all items: {}",
+        Message::StatsFileInvalid => "invalid stats file {}: {}",
+        Message::StatsFileMoved => "moved invalid stats file to {}",
+        Message::StatsFileStuck => "cannot move invalid stats file aside, practice records will not be saved: {}",
+        Message::PracticeSaveFailed => "cannot save practice record to {}: {}",
+        Message::TraineeSelected => "current trainee: {}",
+        Message::FullscreenSize => "--fullscreen needs the screen size, e.g. --fullscreen 1920x1080",
+    }
+}
+
+// 当前界面语言, 在Language::ALL里的下标
+static LANGUAGE: AtomicUsize = AtomicUsize::new(0);
+
+pub fn language() -> Language {
+    Language::ALL[LANGUAGE.load(Ordering::Relaxed)]
+}
+
+pub fn set_language(language: Language) {
+    let i = Language::ALL.iter().position(|&l| l == language).unwrap();
+    LANGUAGE.store(i, Ordering::Relaxed);
+}
+
+// 当前语言下的文字
+pub fn tr(message: Message) -> &'static str {
+    match language() {
+        Language::Chinese => chinese(message),
+        Language::English => english(message),
+    }
+}
+
+// 依次替换文字里的{}
+pub fn format(message: Message, args: &[&dyn std::fmt::Display]) -> String {
+    let mut text = String::new();
+    for (i, part) in tr(message).split("{}").enumerate() {
+        if i > 0 {
+            text += &args.get(i-1).map_or(String::new(), |arg| arg.to_string());
+        }
+        text += part;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_message_translated() {
+        for &message in Message::ALL.iter() {
+            let zh = chinese(message);
+            let en = english(message);
+            assert!(!zh.is_empty() && !en.is_empty(), "{:?}", message);
+            // 两种语言的占位符个数一致, 参数才能对上
+            assert_eq!(zh.matches("{}").count(), en.matches("{}").count(), "{:?}", message);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use minifb::{Key, KeyRepeat, Window};
+
+use car_simulation::json::Value;
+
+// 可以绑定按键的操作
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Forward,
+    Backward,
+    SteerLeft,
+    SteerRight,
+    Record,
+    Ultrasonic,
+    AddCar,
+    SwitchCar,
+    Trailer,
+    SelfAlign,
+    RearSteer,
+    SurroundView,
+    Lidar,
+    PurePursuit,
+    Stanley,
+}
+
+impl Action {
+    pub const ALL: [Action; 15] = [
+        Action::Forward, Action::Backward, Action::SteerLeft, Action::SteerRight,
+        Action::Record, Action::Ultrasonic, Action::AddCar, Action::SwitchCar, Action::Trailer,
+        Action::SelfAlign, Action::RearSteer, Action::SurroundView, Action::Lidar,
+        Action::PurePursuit, Action::Stanley,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Backward => "backward",
+            Action::SteerLeft => "steer_left",
+            Action::SteerRight => "steer_right",
+            Action::Record => "record",
+            Action::Ultrasonic => "ultrasonic",
+            Action::AddCar => "add_car",
+            Action::SwitchCar => "switch_car",
+            Action::Trailer => "trailer",
+            Action::SelfAlign => "self_align",
+            Action::RearSteer => "rear_steer",
+            Action::SurroundView => "surround_view",
+            Action::Lidar => "lidar",
+            Action::PurePursuit => "pure_pursuit",
+            Action::Stanley => "stanley",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().cloned().find(|action| action.name() == name)
+    }
+
+    // 按住不放时是否按系统的按键重复连续触发
+    fn repeats(self) -> bool {
+        matches!(self, Action::SteerLeft | Action::SteerRight)
+    }
+
+    fn default_keys(self) -> &'static [Key] {
+        match self {
+            Action::Forward => &[Key::Up],
+            Action::Backward => &[Key::Down],
+            Action::SteerLeft => &[Key::Left],
+            Action::SteerRight => &[Key::Right],
+            Action::Record => &[Key::R],
+            Action::Ultrasonic => &[Key::U],
+            Action::AddCar => &[Key::N],
+            Action::SwitchCar => &[Key::Tab],
+            Action::Trailer => &[Key::T],
+            Action::SelfAlign => &[Key::C],
+            Action::RearSteer => &[Key::F],
+            Action::SurroundView => &[Key::V],
+            Action::Lidar => &[Key::L],
+            Action::PurePursuit => &[Key::P],
+            Action::Stanley => &[Key::O],
+        }
+    }
+}
+
+// 配置文件里的按键名, 和minifb::Key的变体名一致
+const KEY_NAMES: [(&str, Key); 58] = [
+    ("Key0", Key::Key0), ("Key1", Key::Key1), ("Key2", Key::Key2), ("Key3", Key::Key3), ("Key4", Key::Key4),
+    ("Key5", Key::Key5), ("Key6", Key::Key6), ("Key7", Key::Key7), ("Key8", Key::Key8), ("Key9", Key::Key9),
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E), ("F", Key::F), ("G", Key::G),
+    ("H", Key::H), ("I", Key::I), ("J", Key::J), ("K", Key::K), ("L", Key::L), ("M", Key::M), ("N", Key::N),
+    ("O", Key::O), ("P", Key::P), ("Q", Key::Q), ("R", Key::R), ("S", Key::S), ("T", Key::T), ("U", Key::U),
+    ("V", Key::V), ("W", Key::W), ("X", Key::X), ("Y", Key::Y), ("Z", Key::Z),
+    ("F1", Key::F1), ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4), ("F5", Key::F5), ("F6", Key::F6),
+    ("Up", Key::Up), ("Down", Key::Down), ("Left", Key::Left), ("Right", Key::Right),
+    ("Space", Key::Space), ("Tab", Key::Tab), ("Enter", Key::Enter), ("Backspace", Key::Backspace),
+    ("Comma", Key::Comma), ("Period", Key::Period), ("Slash", Key::Slash), ("Semicolon", Key::Semicolon),
+    ("LeftShift", Key::LeftShift), ("RightShift", Key::RightShift), ("LeftCtrl", Key::LeftCtrl), ("RightCtrl", Key::RightCtrl),
+];
+
+fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter().find(|(n, _)| *n == name).map(|(_, key)| *key)
+}
+
+// 每个操作绑定的按键, 一个操作可以绑定多个键
+pub struct Bindings {
+    keys: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            keys: Action::ALL.iter().map(|&action| (action, action.default_keys().to_vec())).collect(),
+        }
+    }
+}
+
+impl Bindings {
+    // 配置文件的"keys"部分, 例如 {"forward": ["Up", "W"], "steer_left": "A"}
+    // 没写到的操作保留默认按键
+    pub fn from_json(value: &Value) -> Result<Bindings, String> {
+        let mut bindings = Bindings::default();
+        let fields = match value {
+            Value::Object(fields) => fields,
+            _ => return Err("key bindings must be an object".to_string()),
+        };
+        for (name, keys) in fields.iter() {
+            let action = Action::from_name(name).ok_or(format!("unknown action: {}", name))?;
+            let names = match keys {
+                Value::String(key) => vec![key.as_str()],
+                Value::Array(keys) => keys.iter().map(|key| key.as_str().ok_or("key name must be a string"))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(format!("bad keys for {}", name)),
+            };
+            let keys = names.iter().map(|key| key_from_name(key).ok_or(format!("unknown key: {}", key)))
+                .collect::<Result<_, _>>()?;
+            bindings.keys.iter_mut().find(|(a, _)| *a == action).unwrap().1 = keys;
+        }
+        Ok(bindings)
+    }
+
+    fn keys(&self, action: Action) -> &[Key] {
+        self.keys.iter().find(|(a, _)| *a == action).map_or(&[], |(_, keys)| keys.as_slice())
+    }
+}
+
+// 一帧的输入快照: 每帧把所有绑定的操作都读一遍, 边开车边打方向可以同时生效
+pub struct InputState {
+    down: Vec<Action>,
+    pressed: Vec<Action>,
+}
+
+impl InputState {
+    pub fn poll(window: &Window, bindings: &Bindings) -> Self {
+        InputState::from_keys(bindings, |key| window.is_key_down(key), |key, repeat| window.is_key_pressed(key, repeat))
+    }
+
+    fn from_keys(bindings: &Bindings, is_down: impl Fn(Key) -> bool, is_pressed: impl Fn(Key, KeyRepeat) -> bool) -> Self {
+        let mut down = vec![];
+        let mut pressed = vec![];
+        for &action in Action::ALL.iter() {
+            let repeat = if action.repeats() {KeyRepeat::Yes} else {KeyRepeat::No};
+            let keys = bindings.keys(action);
+            if keys.iter().any(|&key| is_down(key)) {
+                down.push(action);
+            }
+            if keys.iter().any(|&key| is_pressed(key, repeat)) {
+                pressed.push(action);
+            }
+        }
+        InputState { down, pressed }
+    }
+
+    // 按住
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    // 这一帧按下(转向键按住时按系统按键重复触发)
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    // 油门: 按住前进为1, 按住后退为-1, 同时按住或都没按为None(保持原来的车速)
+    pub fn drive(&self) -> Option<f32> {
+        match (self.is_down(Action::Forward), self.is_down(Action::Backward)) {
+            (true, false) => Some(1.),
+            (false, true) => Some(-1.),
+            _ => None,
+        }
+    }
+
+    // 这一帧方向盘要转的圈数, 向左为正
+    pub fn steer(&self) -> f32 {
+        let left = if self.pressed(Action::SteerLeft) {1.} else {0.};
+        let right = if self.pressed(Action::SteerRight) {1.} else {0.};
+        left - right
+    }
+
+    // 按着方向键就是握着方向盘
+    pub fn steering(&self) -> bool {
+        self.is_down(Action::SteerLeft) || self.is_down(Action::SteerRight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按住down里的键, 其中pressed是这一帧刚按下的
+    fn input(bindings: &Bindings, down: &[Key], pressed: &[Key]) -> InputState {
+        InputState::from_keys(bindings, |key| down.contains(&key), |key, _| pressed.contains(&key))
+    }
+
+    #[test]
+    fn steer_while_driving() {
+        let bindings = Bindings::default();
+        // 按住上键的同时点左键
+        let state = input(&bindings, &[Key::Up, Key::Left], &[Key::Left]);
+        assert_eq!(state.drive(), Some(1.));
+        assert_eq!(state.steer(), 1.);
+        assert!(state.steering());
+        // 一直按着上键, 右键按键重复
+        let state = input(&bindings, &[Key::Up, Key::Right], &[Key::Right]);
+        assert_eq!(state.drive(), Some(1.));
+        assert_eq!(state.steer(), -1.);
+        // 后退时打方向
+        let state = input(&bindings, &[Key::Down, Key::Left], &[Key::Down, Key::Left]);
+        assert_eq!(state.drive(), Some(-1.));
+        assert_eq!(state.steer(), 1.);
+        // 前进后退同时按住不动, 两个方向键同时按抵消
+        let state = input(&bindings, &[Key::Up, Key::Down, Key::Left, Key::Right], &[Key::Left, Key::Right]);
+        assert_eq!(state.drive(), None);
+        assert_eq!(state.steer(), 0.);
+        let state = input(&bindings, &[], &[]);
+        assert_eq!(state.drive(), None);
+        assert!(!state.steering());
+    }
+
+    #[test]
+    fn parse_bindings() {
+        let value = Value::parse(r#"{"forward": ["Up", "W"], "steer_left": "A", "stanley": []}"#).unwrap();
+        let bindings = Bindings::from_json(&value).unwrap();
+        assert_eq!(bindings.keys(Action::Forward), &[Key::Up, Key::W]);
+        assert_eq!(bindings.keys(Action::SteerLeft), &[Key::A]);
+        assert_eq!(bindings.keys(Action::Stanley), &[]);
+        // 没写到的保留默认
+        assert_eq!(bindings.keys(Action::Backward), &[Key::Down]);
+        let state = input(&bindings, &[Key::W, Key::A], &[Key::A]);
+        assert_eq!(state.drive(), Some(1.));
+        assert_eq!(state.steer(), 1.);
+        // 原来的左键不再转向
+        assert_eq!(input(&bindings, &[Key::Left], &[Key::Left]).steer(), 0.);
+    }
+
+    #[test]
+    fn parse_bindings_errors() {
+        for text in [
+            r#"["Up"]"#,
+            r#"{"fly": "Up"}"#,
+            r#"{"forward": "Hyperspace"}"#,
+            r#"{"forward": [1]}"#,
+            r#"{"forward": 1}"#,
+        ].iter() {
+            assert!(Bindings::from_json(&Value::parse(text).unwrap()).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use std::fmt;
+
+// 远程控制协议和配置文件用到的最小JSON实现
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Value::Number(n) => Some(*n as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Value, String> {
+        let mut parser = Parser { chars: s.chars().collect(), pos: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unexpected trailing characters at {}", parser.pos));
+        }
+        Ok(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Self {
+        // 经过字符串转换, 避免输出 0.1f32 as f64 = 0.10000000149011612
+        Value::Number(n.to_string().parse().unwrap_or(f64::NAN))
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Value::Number(_) => write!(f, "null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            },
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// 数组和对象最多嵌套的层数, 远程客户端发来的"[[[[..."不能把栈递归爆
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", c, self.pos))
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        let end = self.pos + word.len();
+        if end <= self.chars.len() && self.chars[self.pos..end].iter().cloned().eq(word.chars()) {
+            self.pos = end;
+            Ok(value)
+        } else {
+            Err(format!("unexpected token at {}", self.pos))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('{') => self.nested(Parser::object),
+            Some('[') => self.nested(Parser::array),
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("unexpected '{}' at {}", c, self.pos)),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Parser) -> Result<Value, String>) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("nesting deeper than {} at {}", MAX_DEPTH, self.pos));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            if self.peek() != Some('"') {
+                return Err(format!("expected key at {}", self.pos));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                },
+                _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                },
+                _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = *self.chars.get(self.pos).ok_or("unterminated string")?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = *self.chars.get(self.pos).ok_or("unterminated string")?;
+                    self.pos += 1;
+                    match escaped {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        '/' => s.push('/'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let end = self.pos + 4;
+                            let hex: String = self.chars.get(self.pos..end).ok_or("bad unicode escape")?.iter().collect();
+                            let code = u32::from_str_radix(&hex, 16).map_err(|e| e.to_string())?;
+                            s.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                            self.pos = end;
+                        },
+                        c => return Err(format!("bad escape '\\{}'", c)),
+                    }
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self.pos < self.chars.len() && (self.chars[self.pos].is_ascii_digit()
+            || "+-.eE".contains(self.chars[self.pos])) {
+            self.pos += 1;
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        if !valid_number(&s) {
+            return Err(format!("bad number '{}'", s));
+        }
+        s.parse().map(Value::Number).map_err(|_| format!("bad number '{}'", s))
+    }
+}
+
+// JSON的数字格式比f64::from_str严: -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?, 不能有前导0
+fn valid_number(s: &str) -> bool {
+    let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = s.strip_prefix('-').unwrap_or(s);
+    let n = digits(rest);
+    if n == 0 || (n > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    let mut rest = &rest[n..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let n = digits(fraction);
+        if n == 0 {
+            return false;
+        }
+        rest = &fraction[n..];
+    }
+    if let Some(exponent) = rest.strip_prefix(|c| c == 'e' || c == 'E') {
+        let exponent = exponent.strip_prefix(|c| c == '+' || c == '-').unwrap_or(exponent);
+        let n = digits(exponent);
+        if n == 0 {
+            return false;
+        }
+        rest = &exponent[n..];
+    }
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Value, MAX_DEPTH};
+
+    #[test]
+    fn escapes() {
+        assert_eq!(Value::parse(r#""a\"b\\c\/\n\t\u00e9\u4e2d""#), Ok(Value::String("a\"b\\c/\n\té中".to_string())));
+        // 输出再读回来不变
+        let s = Value::from("引号\" 反斜杠\\ 换行\n 控制\u{1}");
+        assert_eq!(Value::parse(&s.to_string()), Ok(s));
+        assert!(Value::parse(r#""\x""#).is_err());
+        assert!(Value::parse(r#""\u12""#).is_err());
+        assert!(Value::parse(r#""abc"#).is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Value::parse("0"), Ok(Value::Number(0.)));
+        assert_eq!(Value::parse("-1.5"), Ok(Value::Number(-1.5)));
+        assert_eq!(Value::parse("2.5e3"), Ok(Value::Number(2500.)));
+        assert_eq!(Value::parse("1E-2"), Ok(Value::Number(0.01)));
+        assert_eq!(Value::from(0.1f32).to_string(), "0.1");
+        // 非有限数输出为null
+        assert_eq!(Value::Number(f64::NAN).to_string(), "null");
+        assert_eq!(Value::parse("-0.5E+2"), Ok(Value::Number(-50.)));
+        assert!(Value::parse("1.2.3").is_err());
+        assert!(Value::parse("-").is_err());
+        // 前导0和f64能解析但JSON不允许的写法
+        for s in ["007", "-01", "00.5", "1.", "-.5", "1e", "1e+", "1.e5", "1+2"].iter() {
+            assert!(Value::parse(s).is_err(), "{:?} should not parse", s);
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let value = Value::parse(r#" {"a": [1, {"b": [true, false, null]}], "c": {}, "d": []} "#).unwrap();
+        assert_eq!(value.get("a").and_then(Value::as_array).map(|a| a.len()), Some(2));
+        assert_eq!(value.get("a").and_then(Value::as_array).and_then(|a| a[1].get("b")).and_then(Value::as_array),
+            Some(&[Value::Bool(true), Value::Bool(false), Value::Null][..]));
+        assert_eq!(value.get("c"), Some(&Value::Object(vec![])));
+        assert_eq!(value.get("d"), Some(&Value::Array(vec![])));
+        assert_eq!(Value::parse(&value.to_string()), Ok(value));
+        let deepest = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(Value::parse(&deepest).is_ok());
+        let too_deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(Value::parse(&too_deep).is_err());
+        // 不能让栈溢出
+        assert!(Value::parse(&"[".repeat(1_000_000)).is_err());
+        assert!(Value::parse(&r#"{"a":"#.repeat(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        for s in ["", " ", "{", "}", "[1,]", "[1 2]", r#"{"a" 1}"#, r#"{a: 1}"#, r#"{"a": 1,}"#, "tru", "nul", "1 2", "@"].iter() {
+            assert!(Value::parse(s).is_err(), "{:?} should not parse", s);
+        }
+    }
+}
@@ -0,0 +1,801 @@
+// 仿真核心: 车、考场、考试评判、传感器和强化学习环境, 不依赖窗口和绘图库
+// 图形界面(main.rs)通过Canvas把View画出来, 无界面环境(env.rs)直接用Sim
+use agent::Agent;
+use back_parking::BackParking;
+use crossing::Crossing;
+use linear_algebra::{Matrix, Vector2D};
+use parallel_parking::ParallelParking;
+use right_angle_turn::RightAngleTurn;
+use std::ops;
+
+use geometry::Segment;
+use obstacle::Obstacle;
+use trailer::{Trailer, TrailerConfig};
+use vehicle::{RearSteerConfig, VehicleSpec};
+
+pub mod linear_algebra;
+pub mod parallel_parking;
+pub mod back_parking;
+pub mod right_angle_turn;
+pub mod geometry;
+pub mod controller;
+pub mod rng;
+pub mod exam;
+pub mod sim;
+pub mod env;
+pub mod ultrasonic;
+pub mod lidar;
+pub mod obstacle;
+pub mod agent;
+pub mod trailer;
+pub mod vehicle;
+pub mod dynamics;
+pub mod steering;
+pub mod crossing;
+pub mod json;
+pub mod protocol;
+pub mod practice;
+
+pub const WINDOW_WIDTH: f32 = WINDOW_HEIGHT+MENU_WIDTH;
+pub const WINDOW_HEIGHT: f32 = 800./SCALE;
+pub const CAR_WIDTH: f32 = 1.837;
+pub const CAR_HEIGHT: f32 = 4.765;
+pub const LOGO_WIDTH: f32 = 1.0;
+// res/tesla.svg是正方形
+pub const LOGO_HEIGHT: f32 = LOGO_WIDTH;
+pub const WHEEL_WIDTH: f32 = 0.215;
+pub const WHEEL_HEIGHT: f32 = WHEEL_WIDTH*0.55*2.+1./39.37*17.;
+pub const TURNING_RADIUS: f32 = 5.5;
+pub const TURNING_COUNT: i32 = 4;
+pub const SCALE: f32 = 30.;
+pub const TRACK_WIDTH: f32 = 1.58;
+pub const FRONT_SUSPENSION: f32 = 0.92;
+pub const REAR_SUSPENSION: f32 = 1.05;
+pub const MIRROR_WIDTH: f32 = 0.08;
+pub const MIRROR_HEIGHT: f32 = 0.35;
+pub const MIRROR_ANGLE: f32 = 70./180.*std::f32::consts::PI;
+pub const MIRROR_ORIGIN_TO_FRONT: f32 = 1.55-MIRROR_WIDTH/2.;
+pub const MENU_WIDTH: f32 = 150./SCALE;
+pub const SPEED: f32 = 4.0;
+
+pub fn new_rotation_matrix(angle: f32) -> Matrix<2, 2> {
+    Matrix::new([
+        [f32::cos(angle), -f32::sin(angle)],
+        [f32::sin(angle), f32::cos(angle)],
+    ])
+}
+
+#[derive(Clone, Copy)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    fn rotate(&self, rotation: Rotation) -> Point {
+        rotation.rotation_matrix * (*self-rotation.origin) + rotation.origin
+    }
+
+    fn forward(&self, distance: f32, rotation_matrix: Matrix<2, 2>) -> Point {
+        point2(self.x, self.y+distance).rotate(Rotation {
+            rotation_matrix,
+            origin: *self,
+        })
+    }
+
+    fn translate(&self, translation: Vector2D) -> Point {
+        *self + translation
+    }
+}
+
+impl From<(f32, f32)> for Point {
+    fn from(p: (f32, f32)) -> Self {
+        point2(p.0, p.1)
+    }
+}
+
+impl From<Point> for (f32, f32) {
+    fn from(p: Point) -> Self {
+        (p.x, p.y)
+    }
+}
+
+impl From<Vector2D> for Point {
+    fn from(p: Vector2D) -> Self {
+        point2(p.x(), p.y())
+    }
+}
+
+impl From<Point> for Vector2D {
+    fn from(p: Point) -> Self {
+        Vector2D::new_from_x_and_y(p.x, p.y)
+    }
+}
+
+impl ops::Sub<Point> for Point {
+    type Output = Vector2D;
+
+    fn sub(self, rhs: Point) -> Self::Output {
+        Vector2D::from(self) - Vector2D::from(rhs)
+    }
+}
+
+impl ops::Add<Vector2D> for Point {
+    type Output = Point;
+
+    fn add(self, rhs: Vector2D) -> Self::Output {
+        (Vector2D::from(self) + rhs).into()
+    }
+}
+
+impl ops::Sub<Vector2D> for Point {
+    type Output = Point;
+
+    fn sub(self, rhs: Vector2D) -> Self::Output {
+        (Vector2D::from(self) - rhs).into()
+    }
+}
+
+impl ops::Add<Point> for Vector2D {
+    type Output = Point;
+
+    fn add(self, rhs: Point) -> Self::Output {
+        rhs + self
+    }
+}
+
+
+pub fn point2(x: f32, y: f32) -> Point {
+    Point {x, y}
+}
+
+pub fn distance_of(x: Point, y: Point) -> f32 {
+    let a = x - y;
+    f32::sqrt(a.x()*a.x() + a.y()*a.y())
+}
+
+#[derive(Clone, Copy)]
+struct Rotation {
+    rotation_matrix: Matrix<2, 2>,
+    origin: Point,
+}
+
+impl Rotation {
+    fn new(angle: f32, origin: Point) -> Rotation {
+        Rotation {
+            rotation_matrix: new_rotation_matrix(angle),
+            origin
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub origin: Point,
+    pub width: f32,
+    pub height: f32,
+    pub rotation_matrix: Matrix<2,2>,
+    pub color: Option<Color>,
+}
+
+impl Rect {
+    pub fn new(origin: Point, width: f32, height: f32, color: Option<Color>) -> Rect {
+        Rect {
+            origin,
+            width,
+            height,
+            rotation_matrix: Matrix::<2, 2>::eye(),
+            color,
+        }
+    }
+
+    pub fn lt(&self) -> Point {
+        point2(self.origin.x - self.width/2., self.origin.y + self.height/2.)
+            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
+    }
+
+    pub fn rt(&self) -> Point {
+        point2(self.origin.x + self.width/2., self.origin.y + self.height/2.)
+            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
+    }
+
+    pub fn lb(&self) -> Point {
+        point2(self.origin.x - self.width/2., self.origin.y - self.height/2.)
+            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
+    }
+
+    pub fn rb(&self) -> Point {
+        point2(self.origin.x + self.width/2., self.origin.y - self.height/2.)
+            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
+    }
+
+    pub fn rotate_self(&mut self, rotation_matrix: Matrix<2,2>) {
+        self.rotation_matrix = rotation_matrix * self.rotation_matrix;
+    }
+
+    fn rotate(&mut self, rotation: Rotation) {
+        self.origin = self.origin.rotate(rotation);
+        self.rotate_self(rotation.rotation_matrix);
+    }
+
+    fn forward(&mut self, distance: f32, rotation_matrix: Matrix<2,2>) {
+        self.origin = self.origin.forward(distance, rotation_matrix);
+    }
+
+    fn translate(&mut self, translation: Vector2D) {
+        self.origin = self.origin.translate(translation);
+    }
+
+    // 顺序: 上, 右, 下, 左
+    pub fn edges(&self) -> [Segment; 4] {
+        [
+            Segment::new(self.lt(), self.rt()),
+            Segment::new(self.rt(), self.rb()),
+            Segment::new(self.rb(), self.lb()),
+            Segment::new(self.lb(), self.lt()),
+        ]
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        let local = self.rotation_matrix.inverse().unwrap() * (p - self.origin);
+        local.x().abs() <= self.width/2. && local.y().abs() <= self.height/2.
+    }
+}
+
+impl View for Rect {
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let corners = [self.lt(), self.rt(), self.rb(), self.lb()];
+        canvas.fill_polygon(&corners.iter().map(|p| p.translate(translation)).collect::<Vec<_>>(), self.color.unwrap());
+    }
+
+    fn relative_translation(&self) -> Vector2D {
+        (0., 0.).into()
+    }
+}
+
+#[derive(Clone)]
+pub struct Car {
+    pub spec: VehicleSpec,
+    lt: Rect,
+    rt: Rect,
+    lb: Rect,
+    rb: Rect,
+    tandem: Vec<Rect>,
+    pub body: Rect,
+    pub steer_angle: f32,
+    // 车标的位置, 图片由画布画
+    logo: Rect,
+    left_mirror: Rect,
+    right_mirror: Rect,
+    pub trailer: Option<Trailer>,
+    // 后轮转向, None时后轮不转
+    pub rear_steer: Option<RearSteerConfig>,
+    // 当前后轮和前轮转角(正切)之比
+    pub rear_ratio: f32,
+}
+
+impl Car {
+    pub fn new(spec: VehicleSpec, body_origin: Point, angle: f32) -> Car {
+        let wheel_color = Color::rgb(0, 0, 0);
+        let (width, height) = (spec.width, spec.length);
+        let wheel = |x: f32, y: f32| Rect::new(point2(body_origin.x+x, body_origin.y+y),
+            spec.wheel_width, spec.wheel_height, Some(wheel_color));
+        let front = height/2.-spec.front_overhang;
+        let back = -height/2.+spec.rear_overhang;
+        let mut body = Rect::new(body_origin, width, height, Some(spec.color));
+        let mut lt = wheel(-spec.track_width/2., front);
+        let mut rt = wheel(spec.track_width/2., front);
+        // 双后轴时lb/rb在虚拟后轴上, 只用于转向计算, 实际的车轮在tandem里
+        let mut lb = wheel(-spec.track_width/2., back);
+        let mut rb = wheel(spec.track_width/2., back);
+        let mut tandem = vec![];
+        if spec.tandem_spacing > 0. {
+            for offset in [-spec.tandem_spacing/2., spec.tandem_spacing/2.].iter() {
+                tandem.push(wheel(-spec.track_width/2., back+offset));
+                tandem.push(wheel(spec.track_width/2., back+offset));
+            }
+        }
+        let mut logo = Rect::new(point2(body_origin.x, body_origin.y+height/2.-0.2-LOGO_HEIGHT/2.),
+            LOGO_WIDTH, LOGO_HEIGHT, None);
+        let mut left_mirror = Rect::new(
+            point2(
+                body_origin.x-width/2.-spec.mirror_height/2.,
+                body_origin.y+height/2.-spec.mirror_to_front,
+            ), spec.mirror_width, spec.mirror_height, Some(spec.color));
+        let mut right_mirror = Rect::new(
+            point2(
+                body_origin.x+width/2.+spec.mirror_height/2.,
+                body_origin.y+height/2.-spec.mirror_to_front,
+            ), spec.mirror_width, spec.mirror_height, Some(spec.color));
+        left_mirror.rotate_self(new_rotation_matrix(std::f32::consts::PI/2.));
+        right_mirror.rotate_self(new_rotation_matrix(std::f32::consts::PI/2.));
+        left_mirror.rotate(Rotation::new(std::f32::consts::PI/2.-spec.mirror_angle, left_mirror.rb()));
+        right_mirror.rotate(Rotation::new(-(std::f32::consts::PI/2.-spec.mirror_angle), right_mirror.rt()));
+        let rotation = Rotation::new(angle, body_origin);
+        body.rotate(rotation);
+        lt.rotate(rotation);
+        rt.rotate(rotation);
+        lb.rotate(rotation);
+        rb.rotate(rotation);
+        for wheel in tandem.iter_mut() {
+            wheel.rotate(rotation);
+        }
+        logo.rotate(rotation);
+        left_mirror.rotate(rotation);
+        right_mirror.rotate(rotation);
+        Car {
+            spec, lt, rt, lb, rb, tandem, body, steer_angle: 0., logo, left_mirror, right_mirror, trailer: None,
+            rear_steer: None, rear_ratio: 0.,
+        }
+    }
+
+    // r: 车轮到转向中心的横向距离, l: 车轮在转向中心前方的距离
+    fn angle_matrix(&self, r: f32, l: f32) -> Matrix<2, 2> {
+        let c = f32::sqrt(r*r + l*l);
+        Matrix { inner: [
+            [r/c, -l/c],
+            [l/c, r/c],
+        ] }
+    }
+
+    // 转向中心在车身坐标系里相对后轴中心的位置, x向右, y向前
+    fn origin_in_body(&self, o: Point) -> Vector2D {
+        self.body.rotation_matrix.inverse().unwrap() * (o - self.back_origin())
+    }
+
+    // 后轴前方axle处一根轴的左右车轮转角
+    fn axle_angle_matrix(&self, o: Option<Point>, axle: f32) -> (Matrix<2,2>, Matrix<2,2>) {
+        match o {
+            Some(o) => {
+                let o = self.origin_in_body(o);
+                let (r, l) = (o.x().abs(), axle - o.y());
+                if o.x() < 0. {
+                    (self.angle_matrix(r-self.track()/2., l), self.angle_matrix(r+self.track()/2., l))
+                } else {
+                    (self.angle_matrix(r+self.track()/2., l).inverse().unwrap(),
+                        self.angle_matrix(r-self.track()/2., l).inverse().unwrap())
+                }
+            },
+            None => {
+                (Matrix::<2, 2>::eye(), Matrix::<2, 2>::eye())
+            }
+        }
+    }
+
+    fn top2_angle_matrix(&self, o: Option<Point>) -> (Matrix<2,2>, Matrix<2,2>) {
+        self.axle_angle_matrix(o, self.wheelbase())
+    }
+
+    fn bottom2_angle_matrix(&self, o: Option<Point>) -> (Matrix<2,2>, Matrix<2,2>) {
+        self.axle_angle_matrix(o, 0.)
+    }
+
+    fn steer(&mut self) {
+        let o_new = self.angle2origin(self.steer_angle);
+        let (lt, rt) = self.top2_angle_matrix(o_new);
+        self.lt.rotation_matrix = lt * self.body.rotation_matrix;
+        self.rt.rotation_matrix = rt * self.body.rotation_matrix;
+        let (lb, rb) = self.bottom2_angle_matrix(o_new);
+        self.lb.rotation_matrix = lb * self.body.rotation_matrix;
+        self.rb.rotation_matrix = rb * self.body.rotation_matrix;
+    }
+
+    // 开启/关闭后轮转向
+    pub fn set_rear_steer(&mut self, config: Option<RearSteerConfig>) {
+        self.rear_steer = config;
+        self.adapt_rear_steer(0.);
+    }
+
+    // 按车速更新后轮转向比例, 每步移动前调用
+    fn adapt_rear_steer(&mut self, speed: f32) {
+        let ratio = self.rear_steer.map_or(0., |config| config.ratio(speed));
+        if ratio != self.rear_ratio {
+            self.rear_ratio = ratio;
+            self.steer();
+        }
+    }
+
+    // 方向盘打满时前外轮的转弯半径
+    pub fn min_turning_radius(&self) -> f32 {
+        let max = self.spec.lock_turns;
+        let o = self.origin_in_body(self.angle2origin(max).unwrap());
+        let r = o.x().abs() + self.track()/2.;
+        let l = self.wheelbase() - o.y();
+        f32::sqrt(r*r + l*l)
+    }
+
+    pub fn forward(&mut self, distance: f32) {
+        let o = self.angle2origin(self.steer_angle);
+        if let Some(o) = o {
+            let angle = distance/distance_of(self.top_origin(), o) 
+                * (if self.steer_angle > 0. {1.} else {-1.});
+            self.rotate(Rotation::new(angle, o));
+        } else {
+            let rotation_matrix = self.body.rotation_matrix;
+            self.lt.forward(distance, rotation_matrix);
+            self.rt.forward(distance, rotation_matrix);
+            self.lb.forward(distance, rotation_matrix);
+            self.rb.forward(distance, rotation_matrix);
+            for wheel in self.tandem.iter_mut() {
+                wheel.forward(distance, rotation_matrix);
+            }
+            self.body.forward(distance, rotation_matrix);
+            self.logo.forward(distance, rotation_matrix);
+            self.left_mirror.forward(distance, rotation_matrix);
+            self.right_mirror.forward(distance, rotation_matrix);
+        }
+        self.update_trailer();
+    }
+
+    // 车移动以后让挂车跟上
+    fn update_trailer(&mut self) {
+        if let Some(mut trailer) = self.trailer {
+            trailer.follow(self);
+            self.trailer = Some(trailer);
+        }
+    }
+
+    // 挂上/摘下挂车, 挂上时挂车和车在一条直线上
+    pub fn set_trailer(&mut self, config: Option<TrailerConfig>) {
+        self.trailer = config.map(|config| Trailer::new(config, self));
+    }
+
+    // 按新尺寸原地重新生成, 车身中心、朝向、方向盘、后轮转向和挂车保留
+    fn set_spec(&mut self, spec: VehicleSpec) {
+        let mut car = Car::new(spec, self.body.origin, self.heading());
+        car.trailer = self.trailer;
+        car.rear_steer = self.rear_steer;
+        car.rear_ratio = self.rear_ratio;
+        car.set_steer_angle(self.steer_angle);
+        car.update_trailer();
+        *self = car;
+    }
+
+    pub(crate) fn rotate(&mut self, rotation: Rotation) {
+        self.lt.rotate(rotation);
+        self.rt.rotate(rotation);
+        self.lb.rotate(rotation);
+        self.rb.rotate(rotation);
+        for wheel in self.tandem.iter_mut() {
+            wheel.rotate(rotation);
+        }
+        self.body.rotate(rotation);
+        self.logo.rotate(rotation);
+        self.left_mirror.rotate(rotation);
+        self.right_mirror.rotate(rotation);
+    }
+
+    pub fn translate(&mut self, translation: Vector2D) {
+        self.lt.translate(translation);
+        self.rt.translate(translation);
+        self.lb.translate(translation);
+        self.rb.translate(translation);
+        for wheel in self.tandem.iter_mut() {
+            wheel.translate(translation);
+        }
+        self.body.translate(translation);
+        self.logo.translate(translation);
+        self.left_mirror.translate(translation);
+        self.right_mirror.translate(translation);
+    }
+
+    // 实际着地的车轮, 双后轴时不包括虚拟后轴
+    pub fn wheels(&self) -> Vec<&Rect> {
+        if self.tandem.is_empty() {
+            vec![&self.lt, &self.rt, &self.lb, &self.rb]
+        } else {
+            std::iter::once(&self.lt).chain(std::iter::once(&self.rt)).chain(self.tandem.iter()).collect()
+        }
+    }
+
+    // 车身朝向相对y轴(初始朝向)的角度, 逆时针为正
+    pub fn heading(&self) -> f32 {
+        f32::atan2(self.body.rotation_matrix.inner[1][0], self.body.rotation_matrix.inner[0][0])
+    }
+
+    // 轴距: 同侧前后轮的距离
+    fn wheelbase(&self) -> f32 {
+        distance_of(self.lt.origin, self.lb.origin)
+    }
+
+    // 轮距: 左右后轮的距离
+    fn track(&self) -> f32 {
+        distance_of(self.lb.origin, self.rb.origin)
+    }
+
+    pub fn back_origin(&self) -> Point {
+        point2((self.lb.origin.x+self.rb.origin.x)/2., (self.lb.origin.y+self.rb.origin.y)/2.)
+    }
+
+    pub fn top_origin(&self) -> Point {
+        point2((self.lt.origin.x+self.rt.origin.x)/2., (self.lt.origin.y+self.rt.origin.y)/2.)
+    }
+
+    // 前轮转角不受后轮转向影响: 反相时转向中心移到两轴之间, 同相时移到后轴后方
+    fn angle2origin(&self, angle: f32) -> Option<Point> {
+        match self.angle2r(angle) {
+            Some(r) => {
+                let back_origin = self.back_origin();
+                let k = self.rear_ratio;
+                let origin_before_trans = point2(back_origin.x-r/(1.-k), back_origin.y-k*self.wheelbase()/(1.-k));
+                Some(origin_before_trans.rotate(Rotation {
+                    rotation_matrix: self.body.rotation_matrix,
+                    origin: back_origin,
+                }))
+            },
+            None => None
+        }
+    }
+
+    fn angle2r(&self, angle: f32) -> Option<f32> {
+        // angle>0: 向左转, r>0; angle<0: 向右转, r<0;
+        if angle == 0. {
+            None
+        } else {
+            Some(self.steer_ratio()/angle)
+        }
+    }
+
+    // 曲率(1/r, 向左为正)对应的steer_angle
+    fn curvature2angle(&self, curvature: f32) -> f32 {
+        self.steer_ratio()*curvature/(1.-self.rear_ratio)
+    }
+
+    fn steer_ratio(&self) -> f32 {
+        let radius = self.spec.turning_radius;
+        self.spec.lock_turns*(f32::sqrt(radius*radius-self.wheelbase()*self.wheelbase())-self.track()/2.)
+    }
+
+    pub fn set_steer_angle(&mut self, angle: f32) {
+        let max = self.spec.lock_turns;
+        self.steer_angle = angle.max(-max).min(max);
+        self.steer();
+    }
+
+    fn left_steer(&mut self) {
+        if self.steer_angle < self.spec.lock_turns {
+            self.set_steer_angle(self.steer_angle.round() + 1.);
+        }
+    }
+
+    fn right_steer(&mut self) {
+        if self.steer_angle > -self.spec.lock_turns {
+            self.set_steer_angle(self.steer_angle.round() - 1.);
+        }
+    }
+
+    // 车头朝向的单位向量
+    pub fn direction(&self) -> Vector2D {
+        self.body.rotation_matrix * Vector2D::new_from_x_and_y(0., 1.)
+    }
+}
+
+impl View for Car {
+    fn relative_translation(&self) -> Vector2D {
+        (MENU_WIDTH, 0.).into()
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let translation = translation + self.relative_translation();
+        if let Some(trailer) = &self.trailer {
+            trailer.draw(canvas, translation);
+        }
+        self.body.draw(canvas, translation);
+        for wheel in self.wheels() {
+            wheel.draw(canvas, translation);
+        }
+        let mut logo = self.logo;
+        logo.translate(translation);
+        canvas.sprite(Sprite::Logo, &logo);
+        self.left_mirror.draw(canvas, translation);
+        self.right_mirror.draw(canvas, translation);
+        if self.rear_steer.is_some() {
+            self.draw_turning_circles(canvas, translation);
+        }
+    }
+}
+
+impl Car {
+    // 车身最外侧扫过的圆和最内侧车轮的圆, 两圆之间是转弯时占用的区域
+    fn draw_turning_circles(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let o = match self.angle2origin(self.steer_angle) {
+            Some(o) => o,
+            None => return,
+        };
+        let outer = [self.body.lt(), self.body.rt(), self.body.lb(), self.body.rb()].iter()
+            .map(|&p| distance_of(p, o)).fold(0., f32::max);
+        let inner = self.wheels().iter()
+            .map(|wheel| distance_of(wheel.origin, o)).fold(f32::INFINITY, f32::min);
+        for (radius, color) in [(outer, Color::rgb(0xd0, 0x40, 0x40)), (inner, Color::rgb(0x40, 0x80, 0xd0))].iter() {
+            canvas.stroke_circle(o + translation, *radius, 1., *color);
+        }
+    }
+}
+
+pub trait Map: View{
+    fn kind(&self) -> MapKind;
+
+    fn car(&self) -> Car;
+
+    // 地图上的边线, 车轮压线即不合格
+    fn lines(&self) -> Vec<Segment>;
+
+    // 需要把车停进去的区域
+    fn goal(&self) -> Rect;
+
+    // 锥桶、立柱、停着的车等静态障碍物, 车身碰到即不合格
+    fn obstacles(&self) -> Vec<Obstacle> {
+        vec![]
+    }
+
+    // 按路点移动的车辆和行人, 每次重置时重新生成
+    fn agents(&self) -> Vec<Agent> {
+        vec![]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MapKind {
+    BackParking,
+    ParallelParking,
+    RightAngleTurn,
+    Crossing,
+}
+
+impl MapKind {
+    pub const ALL: [MapKind; 4] = [MapKind::BackParking, MapKind::ParallelParking, MapKind::RightAngleTurn, MapKind::Crossing];
+
+    // 考场尺寸按车的尺寸放大
+    pub fn build(self, spec: VehicleSpec) -> Box<dyn Map> {
+        match self {
+            MapKind::BackParking => Box::new(BackParking::new(spec)),
+            MapKind::ParallelParking => Box::new(ParallelParking::new(spec)),
+            MapKind::RightAngleTurn => Box::new(RightAngleTurn::new(spec)),
+            MapKind::Crossing => Box::new(Crossing::new(spec)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MapKind::BackParking => "back_parking",
+            MapKind::ParallelParking => "parallel_parking",
+            MapKind::RightAngleTurn => "right_angle_turn",
+            MapKind::Crossing => "crossing",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MapKind> {
+        MapKind::ALL.iter().cloned().find(|kind| kind.name() == name)
+    }
+}
+
+// 颜色, 分量没有预乘alpha
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+}
+
+// 不是由几何图形画出来的图片, 由画布自己加载
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Sprite {
+    Logo,
+}
+
+// View画图用的接口, 坐标都是世界坐标(m, y向上), 由画布换算成像素
+// 仿真本身不依赖绘图库, 图形界面里用raqote实现, 见render.rs
+pub trait Canvas {
+    fn clear(&mut self, color: Color);
+
+    fn fill_polygon(&mut self, points: &[Point], color: Color);
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color);
+
+    // width是线宽(像素)
+    fn stroke_polyline(&mut self, points: &[Point], width: f32, color: Color);
+
+    fn stroke_circle(&mut self, center: Point, radius: f32, width: f32, color: Color);
+
+    // 把图片画到有向矩形outline里
+    fn sprite(&mut self, sprite: Sprite, outline: &Rect);
+}
+
+pub trait View {
+    // 返回该View相对父View的translation
+    fn relative_translation(&self) -> Vector2D;
+
+    // translation: 父View的translation
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Car, distance_of, point2, geometry::dot, linear_algebra::Vector2D, vehicle::{RearSteerConfig, VehicleClass}};
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn axles_from_overhangs() {
+        for class in VehicleClass::ALL.iter() {
+            let spec = class.spec();
+            let car = Car::new(spec, point2(0., 0.), 0.);
+            assert_near(car.top_origin().y, spec.length/2. - spec.front_overhang);
+            assert_near(car.back_origin().y, -spec.length/2. + spec.rear_overhang);
+            assert_near(car.wheelbase(), spec.length - spec.front_overhang - spec.rear_overhang);
+            assert_near(car.track(), spec.track_width);
+        }
+    }
+
+    #[test]
+    fn truck_tandem_axles() {
+        let spec = VehicleClass::Truck.spec();
+        let car = Car::new(spec, point2(0., 0.), 0.);
+        // 着地的是前轮和两根后轴, 虚拟后轴在两根后轴中间
+        let wheels = car.wheels();
+        assert_eq!(wheels.len(), 6);
+        let back = car.back_origin().y;
+        let mut offsets: Vec<f32> = wheels[2..].iter().map(|wheel| wheel.origin.y - back).collect();
+        offsets.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (offset, expected) in offsets.iter().zip([-1., -1., 1., 1.].iter()) {
+            assert_near(*offset, expected*spec.tandem_spacing/2.);
+        }
+    }
+
+    #[test]
+    fn turning_centre_on_rear_axle_line() {
+        for class in VehicleClass::ALL.iter() {
+            let mut car = Car::new(class.spec(), point2(0., 0.), 0.3);
+            for &angle in [1., -2.5, car.spec.lock_turns].iter() {
+                car.set_steer_angle(angle);
+                let o = car.angle2origin(angle).unwrap();
+                // 转向中心在(虚拟)后轴的延长线上
+                assert_near(car.origin_in_body(o).y(), 0.);
+                // 两个前轮都和到转向中心的连线垂直(Ackermann)
+                for wheel in [&car.lt, &car.rt].iter() {
+                    let heading = wheel.rotation_matrix*Vector2D::new_from_x_and_y(0., 1.);
+                    let radius = wheel.origin - o;
+                    assert_near(dot(heading, radius)/distance_of(wheel.origin, o), 0.);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn counter_phase_shrinks_turning_radius() {
+        let config = RearSteerConfig::default();
+        for class in VehicleClass::ALL.iter() {
+            let mut car = Car::new(class.spec(), point2(0., 0.), 0.);
+            let radius = car.min_turning_radius();
+            assert_near(radius, class.spec().turning_radius);
+            car.set_rear_steer(Some(config));
+            assert_near(car.rear_ratio, config.counter_phase);
+            assert!(car.min_turning_radius() < radius - 0.5);
+            // 转向中心移到后轴前面
+            car.set_steer_angle(car.spec.lock_turns);
+            let o = car.angle2origin(car.steer_angle).unwrap();
+            assert!(car.origin_in_body(o).y() > 0.);
+            car.set_rear_steer(None);
+            assert_near(car.min_turning_radius(), radius);
+        }
+    }
+
+    #[test]
+    fn rear_steer_ratio_by_speed() {
+        let config = RearSteerConfig::default();
+        assert_near(config.ratio(0.), config.counter_phase);
+        assert_near(config.ratio(-config.crossover_speed/2.), config.counter_phase/2.);
+        assert_near(config.ratio(config.crossover_speed), 0.);
+        assert_near(config.ratio(config.crossover_speed*2.), config.in_phase);
+        assert_near(config.ratio(config.crossover_speed*5.), config.in_phase);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Canvas, Car, Color, Point, View, MENU_WIDTH, new_rotation_matrix, geometry::{Segment, ray_cast}, linear_algebra::Vector2D, rng::Rng};
+
+// 画出来的测距点的半径(m)
+const POINT_RADIUS: f32 = 0.05;
+
+#[derive(Clone, Copy)]
+pub struct LidarConfig {
+    // 每转一圈的测距点数
+    pub resolution: usize,
+    // 每秒转的圈数
+    pub scan_rate: f32,
+    pub max_range: f32,
+    // 测距高斯噪声的标准差(m)
+    pub noise_std_dev: f32,
+    // 安装位置, 车身坐标系, 单位同UltrasonicSensor::mount
+    pub mount: (f32, f32),
+}
+
+impl Default for LidarConfig {
+    fn default() -> Self {
+        LidarConfig {
+            resolution: 360,
+            scan_rate: 10.,
+            max_range: 30.,
+            noise_std_dev: 0.02,
+            mount: (0., 0.),
+        }
+    }
+}
+
+// 旋转式2D激光雷达, 每次update只更新这段时间内扫过的测距点
+pub struct Lidar {
+    pub config: LidarConfig,
+    rng: Rng,
+    ranges: Vec<f32>,
+    // 每个测距点打到的位置, 没打到为None
+    points: Vec<Option<Point>>,
+    // 下一次要测的测距点
+    next: usize,
+    // 不足一个测距点的扫描进度
+    progress: f32,
+}
+
+impl Lidar {
+    pub fn new(config: LidarConfig, seed: u64) -> Self {
+        Lidar {
+            config,
+            rng: Rng::new(seed),
+            ranges: vec![config.max_range; config.resolution],
+            points: vec![None; config.resolution],
+            next: 0,
+            progress: 0.,
+        }
+    }
+
+    // 第i个测距点相对车头的角度, 逆时针
+    pub fn angle(&self, i: usize) -> f32 {
+        2.*std::f32::consts::PI*(i as f32)/(self.config.resolution as f32)
+    }
+
+    // 最近一圈的测距结果, 下标对应angle(i)
+    pub fn scan(&self) -> &[f32] {
+        &self.ranges
+    }
+
+    // dt为None时一次扫完整圈
+    pub fn update(&mut self, car: &Car, geometry: &[Segment], dt: Option<f32>) {
+        let resolution = self.config.resolution;
+        let count = match dt {
+            Some(dt) => {
+                self.progress += self.config.scan_rate*(resolution as f32)*dt;
+                let count = self.progress.floor();
+                self.progress -= count;
+                (count as usize).min(resolution)
+            },
+            None => resolution,
+        };
+        let body = &car.body;
+        let local = Vector2D::new_from_x_and_y(self.config.mount.0*body.width/2., self.config.mount.1*body.height/2.);
+        let origin = body.origin + body.rotation_matrix*local;
+        for _ in 0..count {
+            let i = self.next;
+            let direction = new_rotation_matrix(self.angle(i)) * car.direction();
+            let distance = ray_cast(origin, direction, geometry, self.config.max_range);
+            if distance < self.config.max_range {
+                let noisy = self.rng.gaussian(distance, self.config.noise_std_dev)
+                    .max(0.).min(self.config.max_range);
+                self.ranges[i] = noisy;
+                self.points[i] = Some(origin + noisy*direction);
+            } else {
+                self.ranges[i] = self.config.max_range;
+                self.points[i] = None;
+            }
+            self.next = (i+1) % resolution;
+        }
+    }
+}
+
+impl View for Lidar {
+    fn relative_translation(&self) -> Vector2D {
+        (MENU_WIDTH, 0.).into()
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let translation = translation + self.relative_translation();
+        for point in self.points.iter().flatten() {
+            canvas.fill_circle(*point + translation, POINT_RADIUS, Color::rgb(0xff, 0x00, 0xff));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lidar, LidarConfig};
+    use crate::{Car, point2, geometry::Segment, vehicle::VehicleClass};
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    // 没有噪声, 车身中心前方5m横着一堵墙
+    fn setup() -> (Car, Vec<Segment>, Lidar) {
+        let car = Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.);
+        let wall = Segment::new(point2(-50., 5.), point2(50., 5.));
+        let lidar = Lidar::new(LidarConfig { noise_std_dev: 0., ..LidarConfig::default() }, 0);
+        (car, vec![wall], lidar)
+    }
+
+    #[test]
+    fn range_to_wall() {
+        let (car, geometry, mut lidar) = setup();
+        lidar.update(&car, &geometry, None);
+        let scan = lidar.scan();
+        assert_near(scan[0], 5.);
+        assert_near(scan[30], 5./f32::cos(lidar.angle(30)));
+        assert_near(scan[330], 5./f32::cos(lidar.angle(330)));
+        // 朝后和超出量程的方向都是max_range
+        assert_eq!(scan[180], lidar.config.max_range);
+        assert_eq!(scan[85], lidar.config.max_range);
+    }
+
+    #[test]
+    fn partial_sweep() {
+        let (car, geometry, mut lidar) = setup();
+        // 每秒10圈, 360个点, 0.01s扫过36个点
+        lidar.update(&car, &geometry, Some(0.01));
+        assert_near(lidar.scan()[35], 5./f32::cos(lidar.angle(35)));
+        assert_eq!(lidar.scan()[36], lidar.config.max_range);
+        assert_eq!(lidar.scan()[359], lidar.config.max_range);
+        lidar.update(&car, &geometry, Some(0.09));
+        assert_near(lidar.scan()[359], 5./f32::cos(lidar.angle(359)));
+    }
+
+    #[test]
+    fn noise_is_seeded() {
+        let (car, geometry, _) = setup();
+        let config = LidarConfig::default();
+        let mut a = Lidar::new(config, 3);
+        let mut b = Lidar::new(config, 3);
+        a.update(&car, &geometry, None);
+        b.update(&car, &geometry, None);
+        assert_eq!(a.scan(), b.scan());
+        assert!((a.scan()[0] - 5.).abs() < 5.*config.noise_std_dev);
+    }
+}
@@ -1,38 +1,48 @@
-use back_parking::BackParking;
-use linear_algebra::{Matrix, Vector2D};
-use minifb::{Window, WindowOptions, Key, KeyRepeat, MouseButton};
-use parallel_parking::ParallelParking;
-use right_angle_turn::RightAngleTurn;
-use raqote::{DrawTarget, SolidSource, Source, DrawOptions, PathBuilder, ExtendMode, FilterMode, Transform, BlendMode, AntialiasMode};
-use std::{ops, time::{SystemTime, Duration}};
-
-use button::Button;
-
-mod linear_algebra;
-mod parallel_parking;
-mod back_parking;
-mod right_angle_turn;
-mod button;
-
-const WINDOW_WIDTH: f32 = WINDOW_HEIGHT+MENU_WIDTH;
-const WINDOW_HEIGHT: f32 = 800./SCALE;
-const CAR_WIDTH: f32 = 1.837;
-const CAR_HEIGHT: f32 = 4.765;
-const LOGO_WIDTH: f32 = 1.0;
-const WHEEL_WIDTH: f32 = 0.215;
-const WHEEL_HEIGHT: f32 = WHEEL_WIDTH*0.55*2.+1./39.37*17.;
-const TURNING_RADIUS: f32 = 5.5;
-const TURNING_COUNT: i32 = 4;
-const SCALE: f32 = 30.;
-const TRACK_WIDTH: f32 = 1.58;
-const FRONT_SUSPENSION: f32 = 0.92;
-const REAR_SUSPENSION: f32 = 1.05;
-const MIRROR_WIDTH: f32 = 0.08;
-const MIRROR_HEIGHT: f32 = 0.35;
-const MIRROR_ANGLE: f32 = 70./180.*std::f32::consts::PI;
-const MIRROR_ORIGIN_TO_FRONT: f32 = 1.55-MIRROR_WIDTH/2.;
-const MENU_WIDTH: f32 = 150./SCALE;
-const SPEED: f32 = 4.0;
+use car_simulation::{Point, View, MapKind, point2, MENU_WIDTH, SCALE, SPEED, WINDOW_HEIGHT, WINDOW_WIDTH,
+    controller::{self, Controller, PurePursuit, Recorder, Stanley}, json, protocol::RemoteCommand, lidar::{Lidar, LidarConfig}, sim::{Gear, Sim},
+    trailer::TrailerConfig, vehicle::{RearSteerConfig, VehicleClass, VehicleSpec}, dynamics::{BicycleModel, DynamicsConfig, TireModel},
+    steering::{SteeringActuator, SteeringConfig, WHEEL_TURNS_TO_LOCK}, practice::AttemptTracker};
+use minifb::{Window, WindowOptions, MouseButton, ScaleMode};
+use raqote::{DrawTarget, SolidSource, Transform};
+use std::time::{SystemTime, Duration};
+
+use remote::RemoteServer;
+use hud::{Hud, WheelDrag};
+use input::{Action, Bindings, InputState};
+use device::DeviceInput;
+use i18n::{Language, Message};
+use ui::{Panel, UiEvent};
+use surround_view::SurroundView;
+use session::ExamSession;
+use stats::StatsStore;
+use render::Painter;
+
+mod remote;
+mod surround_view;
+mod hud;
+mod input;
+mod device;
+mod fonts;
+mod i18n;
+mod ui;
+mod session;
+mod stats;
+mod render;
+
+// 开启动力学模型时键盘控制的车速, 用于中低速操控练习
+const HANDLING_SPEED: f32 = 12.0;
+// 开启松手回正时每行驶1m回正的圈数
+const SELF_ALIGN: f32 = 0.1;
+const CONFIG_PATH: &str = "config.json";
+const STATS_PATH: &str = "stats.json";
+const DEFAULT_TRAINEE: &str = "default";
+// 菜单面板到窗口边缘的距离(像素)
+const MENU_PADDING: f32 = 10.;
+const SETTINGS_WIDTH: f32 = 200.;
+const LOOKAHEAD: f32 = 2.0;
+const STANLEY_GAIN: f32 = 1.5;
+const RECORD_SPACING: f32 = 0.2;
+const REMOTE_PORT: u16 = 7878;
 
 fn real2pixel(p: Point) -> Point {
     point2(p.x * SCALE, (WINDOW_HEIGHT - p.y) * SCALE)
@@ -42,509 +52,266 @@ fn pixel2real(p: Point) -> Point {
     point2(p.x / SCALE, WINDOW_HEIGHT-p.y/SCALE)
 }
 
-fn new_rotation_matrix(angle: f32) -> Matrix<2, 2> {
-    Matrix::new([
-        [f32::cos(angle), -f32::sin(angle)],
-        [f32::sin(angle), f32::cos(angle)],
-    ])
-}
-
-#[derive(Clone, Copy)]
-pub struct Point {
-    x: f32,
-    y: f32,
-}
-
-impl Point {
-    fn rotate(&self, rotation: Rotation) -> Point {
-        rotation.rotation_matrix * (*self-rotation.origin) + rotation.origin
-    }
-
-    fn forward(&self, distance: f32, rotation_matrix: Matrix<2, 2>) -> Point {
-        point2(self.x, self.y+distance).rotate(Rotation {
-            rotation_matrix,
-            origin: *self,
-        })
-    }
-
-    fn translate(&self, translation: Vector2D) -> Point {
-        *self + translation
-    }
-}
-
-impl From<(f32, f32)> for Point {
-    fn from(p: (f32, f32)) -> Self {
-        point2(p.0, p.1)
-    }
-}
-
-impl From<Point> for raqote::Point {
-    fn from(p: Point) -> Self {
-        raqote::Point::new(p.x, p.y)
-    }
-}
-
-impl From<Point> for (f32, f32) {
-    fn from(p: Point) -> Self {
-        (p.x, p.y)
-    }
-}
-
-impl From<Vector2D> for Point {
-    fn from(p: Vector2D) -> Self {
-        point2(p.x(), p.y())
-    }
-}
-
-impl From<Point> for Vector2D {
-    fn from(p: Point) -> Self {
-        Vector2D::new_from_x_and_y(p.x, p.y)
-    }
-}
-
-impl ops::Sub<Point> for Point {
-    type Output = Vector2D;
-
-    fn sub(self, rhs: Point) -> Self::Output {
-        Vector2D::from(self) - Vector2D::from(rhs)
-    }
-}
-
-impl ops::Add<Vector2D> for Point {
-    type Output = Point;
-
-    fn add(self, rhs: Vector2D) -> Self::Output {
-        (Vector2D::from(self) + rhs).into()
-    }
-}
-
-impl ops::Add<Point> for Vector2D {
-    type Output = Point;
-
-    fn add(self, rhs: Point) -> Self::Output {
-        rhs + self
-    }
-}
-
-
-fn point2(x: f32, y: f32) -> Point {
-    Point {x, y}
-}
-
-fn distance_of(x: Point, y: Point) -> f32 {
-    let a = x - y;
-    f32::sqrt(a.x()*a.x() + a.y()*a.y())
-}
-
-#[derive(Clone, Copy)]
-struct Rotation {
-    rotation_matrix: Matrix<2, 2>,
-    origin: Point,
-}
-
-impl Rotation {
-    fn new(angle: f32, origin: Point) -> Rotation {
-        Rotation {
-            rotation_matrix: new_rotation_matrix(angle),
-            origin
-        }
-    }
-}
-
-#[derive(Clone, Copy)]
-struct Rect {
-    origin: Point,
+// 窗口布局(像素): 菜单固定在左上角不缩放, 右边剩下的区域是地图视口; 仪表排在菜单下面, 窗口太矮时移到地图视口里
+// 地图按WINDOW_WIDTH/WINDOW_HEIGHT和SCALE画, 再整体等比缩放到视口中间
+#[derive(Clone, Copy, PartialEq)]
+struct Layout {
     width: f32,
     height: f32,
-    rotation_matrix: Matrix<2,2>,
-    color: Option<SolidSource>,
 }
 
-impl Rect {
-    fn new(origin: Point, width: f32, height: f32, color: Option<SolidSource>) -> Rect {
-        Rect {
-            origin,
-            width,
-            height,
-            rotation_matrix: Matrix::<2, 2>::eye(),
-            color,
-        }
-    }
-
-    fn lt(&self) -> Point {
-        point2(self.origin.x - self.width/2., self.origin.y + self.height/2.)
-            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
-    }
-
-    fn rt(&self) -> Point {
-        point2(self.origin.x + self.width/2., self.origin.y + self.height/2.)
-            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
-    }
-
-    fn lb(&self) -> Point {
-        point2(self.origin.x - self.width/2., self.origin.y - self.height/2.)
-            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
-    }
-
-    fn rb(&self) -> Point {
-        point2(self.origin.x + self.width/2., self.origin.y - self.height/2.)
-            .rotate(Rotation { rotation_matrix: self.rotation_matrix, origin: self.origin })
-    }
-
-    fn path(&self, translation: Vector2D) -> raqote::Path {
-        let mut pb = PathBuilder::new();
-        let (x, y) = real2pixel(self.lt().translate(translation)).into();
-        pb.move_to(x, y);
-        let (x, y) = real2pixel(self.rt().translate(translation)).into();
-        pb.line_to(x, y);
-        let (x, y) = real2pixel(self.rb().translate(translation)).into();
-        pb.line_to(x, y);
-        let (x, y) = real2pixel(self.lb().translate(translation)).into();
-        pb.line_to(x, y);
-        pb.close();
-        pb.finish()
+impl Layout {
+    fn new((width, height): (usize, usize)) -> Self {
+        Layout { width: width as f32, height: height as f32 }
     }
 
-    fn rotate_self(&mut self, rotation_matrix: Matrix<2,2>) {
-        self.rotation_matrix = rotation_matrix * self.rotation_matrix;
+    // 地图视口: (left, top, width, height)
+    fn viewport(&self) -> (f32, f32, f32, f32) {
+        let left = MENU_WIDTH*SCALE;
+        (left, 0., (self.width - left).max(1.), self.height.max(1.))
     }
 
-    fn rotate(&mut self, rotation: Rotation) {
-        self.origin = self.origin.rotate(rotation);
-        self.rotate_self(rotation.rotation_matrix);
+    // 按SCALE画出的地图像素 -> 窗口像素, scale是设置面板里的比例尺(每米的像素数), 绕地图中心缩放
+    fn world_transform(&self, scale: f32) -> Transform {
+        let (map_width, map_height) = ((WINDOW_WIDTH-MENU_WIDTH)*SCALE, WINDOW_HEIGHT*SCALE);
+        let (left, top, width, height) = self.viewport();
+        let k = scale/SCALE*(width/map_width).min(height/map_height);
+        let (center_x, center_y) = (MENU_WIDTH*SCALE + map_width/2., map_height/2.);
+        Transform::row_major(k, 0., 0., k, left + width/2. - k*center_x, top + height/2. - k*center_y)
     }
 
-    fn forward(&mut self, distance: f32, rotation_matrix: Matrix<2,2>) {
-        self.origin = self.origin.forward(distance, rotation_matrix);
+    // 之后画的地图、车和传感器按world变换, 只画在地图视口里
+    fn begin_world(&self, dt: &mut DrawTarget, world: &Transform) {
+        let (left, top, width, height) = self.viewport();
+        dt.push_clip_rect(raqote::IntRect::new(raqote::IntPoint::new(left as i32, top as i32),
+            raqote::IntPoint::new((left + width) as i32, (top + height) as i32)));
+        dt.set_transform(world);
     }
-}
 
-impl View for Rect {
-    fn draw(&self, dt: &mut DrawTarget, translation: Vector2D) {
-        dt.fill(
-            &self.path(translation),
-            &Source::Solid(self.color.unwrap()),
-            &DrawOptions {
-                blend_mode: BlendMode::Src,
-                alpha: 1.,
-                antialias: AntialiasMode::Gray,
-            }
-        );
+    fn end_world(&self, dt: &mut DrawTarget) {
+        dt.set_transform(&Transform::identity());
+        dt.pop_clip();
     }
 
-    fn relative_translation(&self) -> Vector2D {
-        (0., 0.).into()
+    // 仪表的方向盘中心(像素)和是否盖在地图上: 菜单下面放得下就接着菜单往下排,
+    // 窗口太矮时贴着底边画在地图视口左下角
+    fn hud_center(&self, menu_bottom: f32) -> ((f32, f32), bool) {
+        let below_menu = menu_bottom + MENU_PADDING + hud::TOP;
+        if below_menu + hud::BOTTOM <= self.height {
+            ((MENU_WIDTH*SCALE/2., below_menu), false)
+        } else {
+            let (left, ..) = self.viewport();
+            let y = (self.height - MENU_PADDING - hud::BOTTOM).max(hud::TOP);
+            ((left + MENU_PADDING + hud::HALF_WIDTH, y), true)
+        }
     }
 }
 
-struct Logo {
-    data: Vec<u32>,
-    outline: Rect,
+// 键盘、设备和远程控制共用的最高车速, 倒车不超过SPEED
+fn top_speed(gear: Gear, max_speed: f32) -> f32 {
+    if gear == Gear::Reverse {max_speed.min(SPEED)} else {max_speed}
 }
 
-impl Logo {
-    fn new(path: &std::path::Path, origin: Point, width: f32) -> Self {
-        let svg = usvg::Tree::from_data(&std::fs::read(path).unwrap(), &usvg::Options::default().to_ref()).unwrap();
-        let (svg_ori_width, svg_ori_height) = (svg.svg_node().size.width(), svg.svg_node().size.height());
-        let height = (svg_ori_height/svg_ori_width) as f32 * width;
-        let mut pixmap = tiny_skia::Pixmap::new((width*SCALE) as u32, (height*SCALE) as u32).unwrap();
-        resvg::render(&svg, usvg::FitTo::Width((width*SCALE) as u32), pixmap.as_mut()).unwrap();
-        let mut data = vec![];
-        for chunk in pixmap.data().chunks(4) {
-            if let &[r, g, b, a] = chunk {
-                data.push(u32::from_be_bytes([a, r, g, b]));
-            }
-        }
-        Logo {
-            data,
-            outline: Rect::new(origin, width, height, None),
-        }
-    }
-
-    fn rotate(&mut self, rotation: Rotation) {
-        self.outline.rotate(rotation);
-    }
-
-    fn forward(&mut self, distance: f32, rotation_matrix: Matrix<2,2>) {
-        self.outline.forward(distance, rotation_matrix);
-    }
+// 菜单、远程控制和考试换项目都从这里换地图, 录的轨迹、参考路径和自动驾驶都是上一个考场的, 一起清掉
+fn load_course(sim: &mut Sim, kind: MapKind, spec: VehicleSpec, recorder: &mut Option<Recorder>,
+    reference: &mut Option<controller::Path>, autopilot: &mut Option<Box<dyn Controller>>) {
+    sim.load(kind.build(spec));
+    *recorder = None;
+    *reference = None;
+    *autopilot = None;
 }
 
-impl View for Logo {
-    fn relative_translation(&self) -> Vector2D {
-        (0., 0.).into()
-    }
-
-    fn draw(&self, dt: &mut DrawTarget, translation: Vector2D) {
-        let image = raqote::Image {
-            width: (self.outline.width*SCALE) as i32,
-            height: (self.outline.height*SCALE) as i32,
-            data: self.data.as_slice()
-        };
-        let rot_inv = self.outline.rotation_matrix.inverse().unwrap();
-        let origin = self.outline.origin.translate(translation);
-        dt.fill(
-            &self.outline.path(translation), 
-            &Source::Image(
-                image,
-                ExtendMode::Pad,
-                FilterMode::Bilinear,
-                Transform::row_major(1./SCALE, 0., 0., -1./SCALE, 0., WINDOW_HEIGHT)
-                    .post_transform(&Transform::create_translation(-origin.x, -origin.y))
-                    .post_transform(&Transform::row_major(
-                        rot_inv.inner[0][0],
-                        rot_inv.inner[1][0],
-                        rot_inv.inner[0][1],
-                        rot_inv.inner[1][1],
-                        self.outline.width/2.,
-                        self.outline.height/2.,
-                    )).post_transform(&Transform::row_major(
-                        SCALE, 0., 0., -SCALE, 0., self.outline.height*SCALE
-                    ))
-            ), 
-            &DrawOptions {
-                blend_mode: BlendMode::SrcOver,
-                alpha: 1.,
-                antialias: AntialiasMode::Gray,
-            }
-        );
-    }
+// 菜单栏: 选地图、得分、显示和操控的开关、重新开始、切换语言
+fn build_menu() -> Panel {
+    let mut menu = Panel::new(MENU_PADDING, MENU_PADDING, MENU_WIDTH*SCALE - 2.*MENU_PADDING);
+    menu.add_list("map", MapKind::ALL.iter().map(|&kind| Message::from(kind).into()).collect(), 0);
+    menu.add_label("score", Message::Score);
+    menu.add_toggle("ultrasonic", Message::Ultrasonic, true);
+    menu.add_toggle("surround_view", Message::SurroundView, false);
+    menu.add_toggle("rear_steer", Message::RearSteer, false);
+    menu.add_toggle("self_align", Message::SelfAlign, false);
+    menu.add_slider("steering_rate", Message::SteeringRate, 0.5, 4., SteeringConfig::default().max_rate);
+    menu.add_button("exam", Message::ExamMode);
+    menu.add_button("stats", Message::Stats);
+    menu.add_button("settings", Message::Settings);
+    menu.add_button("reset", Message::Reset);
+    menu.add_button("language", Message::LanguageName);
+    menu
 }
 
-struct Car {
-    lt: Rect,
-    rt: Rect,
-    lb: Rect,
-    rb: Rect,
-    body: Rect,
-    steer_angle: i32,
-    logo: Logo,
-    left_mirror: Rect,
-    right_mirror: Rect,
+// 菜单上的开关被点了, 或者按了对应的快捷键, 返回切换后的状态
+fn switched(events: &[UiEvent], id: &str, pressed: bool, current: bool) -> Option<bool> {
+    let toggled = events.iter().find_map(|event| match *event {
+        UiEvent::Toggled(toggle, on) if toggle == id => Some(on),
+        _ => None,
+    });
+    toggled.or(if pressed {Some(!current)} else {None})
 }
 
-impl Car {
-    fn new(body_origin: Point, angle: f32) -> Car {
-        let body_color = SolidSource::from_unpremultiplied_argb(0xff, 24, 174, 219);
-        let wheel_color = SolidSource::from_unpremultiplied_argb(0xff, 0, 0, 0);
-        let mut body = Rect::new(body_origin, CAR_WIDTH, CAR_HEIGHT, Some(body_color));
-        let mut lt = Rect::new(point2(body.origin.x-TRACK_WIDTH/2., CAR_HEIGHT/2.+body.origin.y-FRONT_SUSPENSION),
-        WHEEL_WIDTH, WHEEL_HEIGHT, Some(wheel_color));
-        let mut rt = Rect::new(point2(body.origin.x+TRACK_WIDTH/2., CAR_HEIGHT/2.+body.origin.y-FRONT_SUSPENSION),
-        WHEEL_WIDTH, WHEEL_HEIGHT, Some(wheel_color));
-        let mut lb = Rect::new(point2(body.origin.x-TRACK_WIDTH/2., -CAR_HEIGHT/2.+body.origin.y+REAR_SUSPENSION),
-        WHEEL_WIDTH, WHEEL_HEIGHT, Some(wheel_color));
-        let mut rb = Rect::new(point2(body.origin.x+TRACK_WIDTH/2., -CAR_HEIGHT/2.+body.origin.y+REAR_SUSPENSION),
-        WHEEL_WIDTH, WHEEL_HEIGHT, Some(wheel_color));
-        let mut logo = Logo::new(
-            std::path::Path::new("res/tesla.svg"),
-            point2(body_origin.x, body_origin.y+CAR_HEIGHT/2.-0.2),
-            LOGO_WIDTH,
-        );
-        logo.outline.origin.y -= logo.outline.height/2.;
-        let mut left_mirror = Rect::new(
-            point2(
-                body_origin.x-CAR_WIDTH/2.-MIRROR_HEIGHT/2.,
-                body_origin.y+CAR_HEIGHT/2.-MIRROR_ORIGIN_TO_FRONT,
-            ), MIRROR_WIDTH, MIRROR_HEIGHT, Some(body_color));
-        let mut right_mirror = Rect::new(
-            point2(
-                body_origin.x+CAR_WIDTH/2.+MIRROR_HEIGHT/2.,
-                body_origin.y+CAR_HEIGHT/2.-MIRROR_ORIGIN_TO_FRONT,
-            ), MIRROR_WIDTH, MIRROR_HEIGHT, Some(body_color));
-        left_mirror.rotate_self(new_rotation_matrix(std::f32::consts::PI/2.));
-        right_mirror.rotate_self(new_rotation_matrix(std::f32::consts::PI/2.));
-        left_mirror.rotate(Rotation::new(std::f32::consts::PI/2.-MIRROR_ANGLE, left_mirror.rb()));
-        right_mirror.rotate(Rotation::new(-(std::f32::consts::PI/2.-MIRROR_ANGLE), right_mirror.rt()));
-        let rotation = Rotation::new(angle, body_origin);
-        body.rotate(rotation);
-        lt.rotate(rotation);
-        rt.rotate(rotation);
-        lb.rotate(rotation);
-        rb.rotate(rotation);
-        logo.rotate(rotation);
-        left_mirror.rotate(rotation);
-        right_mirror.rotate(rotation);
-        Car {
-            lt, rt, lb, rb, body, steer_angle: 0, logo, left_mirror, right_mirror
-        }
-    }
-
-    fn angle_matrix(&self, r: f32) -> Matrix<2, 2> {
-        let c = f32::sqrt(r*r + self.L()*self.L());
-        Matrix { inner: [
-            [r/c, -self.L()/c],
-            [self.L()/c, r/c],
-        ] }
-    }
-
-    fn small_angle_matrix(&self, r: f32) -> Matrix<2,2> {
-        self.angle_matrix(r+self.T()/2.)
-    }
-
-    fn big_angle_matrix(&self, r: f32) -> Matrix<2,2> {
-        self.angle_matrix(r-self.T()/2.)
-    }
-
-    fn top2_angle_matrix(&self, o: Option<Point>) -> (Matrix<2,2>, Matrix<2,2>) {
-        match o {
-            Some(o) => {
-                let r = distance_of(self.back_origin(), o);
-                if distance_of(self.lt.origin, o) < distance_of(self.rt.origin, o) {
-                    (self.big_angle_matrix(r), self.small_angle_matrix(r))
-                } else {
-                    (self.small_angle_matrix(r).inverse().unwrap(), self.big_angle_matrix(r).inverse().unwrap())
-                }
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // --vehicle car/truck/bus: 车型, 考场按车型放大; 写错了用小型汽车
+    let vehicle = args.iter().position(|arg| arg == "--vehicle")
+        .and_then(|i| args.get(i+1))
+        .map_or(VehicleClass::Car, |name| VehicleClass::from_name(name).unwrap_or_else(|| {
+            let names: Vec<_> = VehicleClass::ALL.iter().map(|class| class.name()).collect();
+            println!("unknown vehicle {}, expected {}, using car", name, names.join("/"));
+            VehicleClass::Car
+        }));
+    // --remote [port]: 在本地端口上接受JSON远程控制, 协议见remote.rs
+    // 端口被占用时不开远程控制, 照常启动
+    let mut remote = args.iter().position(|arg| arg == "--remote").and_then(|i| {
+        let port = args.get(i+1).and_then(|s| s.parse().ok()).unwrap_or(REMOTE_PORT);
+        match RemoteServer::bind(port) {
+            Ok(server) => {
+                println!("remote control listening on 127.0.0.1:{}", port);
+                Some(server)
             },
-            None => {
-                (Matrix::<2, 2>::eye(), Matrix::<2, 2>::eye())
-            }
-        }
-    }
-
-    fn steer(&mut self) {
-        let o_new = self.angle2origin(self.steer_angle);
-        let (lt, rt) = self.top2_angle_matrix(o_new);
-        self.lt.rotation_matrix = lt * self.body.rotation_matrix;
-        self.rt.rotation_matrix = rt * self.body.rotation_matrix;
-    }
-
-    fn forward(&mut self, distance: f32) {
-        let o = self.angle2origin(self.steer_angle);
-        if let Some(o) = o {
-            let angle = distance/distance_of(self.top_origin(), o) 
-                * (if self.steer_angle > 0 {1.} else {-1.});
-            let rotation = Rotation::new(angle, o);
-            self.lt.rotate(rotation);
-            self.rt.rotate(rotation);
-            self.lb.rotate(rotation);
-            self.rb.rotate(rotation);
-            self.body.rotate(rotation);
-            self.logo.rotate(rotation);
-            self.left_mirror.rotate(rotation);
-            self.right_mirror.rotate(rotation);
-        } else {
-            let rotation_matrix = self.body.rotation_matrix;
-            self.lt.forward(distance, rotation_matrix);
-            self.rt.forward(distance, rotation_matrix);
-            self.lb.forward(distance, rotation_matrix);
-            self.rb.forward(distance, rotation_matrix);
-            self.body.forward(distance, rotation_matrix);
-            self.logo.forward(distance, rotation_matrix);
-            self.left_mirror.forward(distance, rotation_matrix);
-            self.right_mirror.forward(distance, rotation_matrix);
-        }
-    }
-
-    fn L(&self) -> f32 {
-        distance_of(self.lt.origin, self.lb.origin)
-    }
-
-    fn T(&self) -> f32 {
-        distance_of(self.lb.origin, self.rb.origin)
-    }
-
-    fn back_origin(&self) -> Point {
-        point2((self.lb.origin.x+self.rb.origin.x)/2., (self.lb.origin.y+self.rb.origin.y)/2.)
-    }
-
-    fn top_origin(&self) -> Point {
-        point2((self.lt.origin.x+self.rt.origin.x)/2., (self.lt.origin.y+self.rt.origin.y)/2.)
-    }
-
-    fn angle2origin(&self, angle: i32) -> Option<Point> {
-        match self.angle2r(angle) {
-            Some(r) => {
-                let back_origin = self.back_origin();
-                let origin_before_trans = point2(back_origin.x-r, back_origin.y);
-                Some(origin_before_trans.rotate(Rotation {
-                    rotation_matrix: self.body.rotation_matrix,
-                    origin: back_origin,
-                }))
+            Err(err) => {
+                println!("cannot listen on 127.0.0.1:{}, remote control disabled: {}", port, err);
+                None
             },
-            None => None
         }
-    }
-
-    fn angle2r(&self, angle: i32) -> Option<f32> {
-        // angle>0: 向左转, r>0; angle<0: 向右转, r<0;
-        if angle == 0 {
+    });
+    // --dynamics linear/pacejka: 用带轮胎侧偏的动力学模型代替运动学模型; 写错了用运动学模型
+    let dynamics = args.iter().position(|arg| arg == "--dynamics")
+        .and_then(|i| args.get(i+1))
+        .and_then(|name| TireModel::from_name(name).or_else(|| {
+            let names: Vec<_> = TireModel::ALL.iter().map(|tire| tire.name()).collect();
+            println!("unknown tire model {}, expected {}, using the kinematic model", name, names.join("/"));
             None
-        } else {
-            Some(
-                (TURNING_COUNT as f32)*(f32::sqrt(TURNING_RADIUS*TURNING_RADIUS-self.L()*self.L())-self.T()/2.)
-                    /(angle as f32)
-            )
-        }
-    }
-
-    fn left_steer(&mut self) {
-        if self.steer_angle < TURNING_COUNT {
-            self.steer_angle += 1;
-            self.steer();
-        }
-    }
-
-    fn right_steer(&mut self) {
-        if self.steer_angle > -TURNING_COUNT {
-            self.steer_angle -= 1;
-            self.steer();
+        }))
+        .map(|tire| BicycleModel::new(DynamicsConfig::new(tire)));
+    let default_speed = if dynamics.is_some() {HANDLING_SPEED} else {SPEED};
+    let mut max_speed = default_speed;
+    // --config path: 配置文件, 默认读当前目录下的config.json, 没有就全用默认值
+    // 配置文件是用户手改的, 写错了只打印出来, 用默认值接着跑
+    let config_path = args.iter().position(|arg| arg == "--config").and_then(|i| args.get(i+1));
+    let config = match std::fs::read_to_string(config_path.map_or(CONFIG_PATH, |path| path.as_str())) {
+        Ok(text) => json::Value::parse(&text).unwrap_or_else(|err| {
+            println!("invalid config file, using defaults: {}", err);
+            json::Value::Null
+        }),
+        Err(err) => {
+            if config_path.is_some() {
+                println!("cannot read config file, using defaults: {}", err);
+            }
+            json::Value::Null
+        },
+    };
+    let bindings = match config.get("keys").map(Bindings::from_json) {
+        Some(Ok(bindings)) => bindings,
+        Some(Err(err)) => {
+            println!("invalid key bindings, using the default keys: {}", err);
+            Bindings::default()
+        },
+        None => Bindings::default(),
+    };
+    // 没有能显示中文的字体时只能用英文界面
+    let (font, chinese) = match fonts::load(config.get("font")) {
+        Some(font) => (font, true),
+        None => match fonts::load_latin() {
+            Some(font) => (font, false),
+            // 没有字体没法画界面
+            None => std::process::exit(1),
+        },
+    };
+    // 界面语言, 配置文件的"language": "zh"/"en", 也可以点菜单里的语言按钮切换
+    if let Some(name) = config.get("language").and_then(json::Value::as_str) {
+        match Language::from_name(name) {
+            Some(language) => i18n::set_language(language),
+            None => println!("unknown language {:?}, expected zh/en", name),
         }
     }
-}
-
-impl View for Car {
-    fn relative_translation(&self) -> Vector2D {
-        (MENU_WIDTH, 0.).into()
-    }
-
-    fn draw(&self, dt: &mut DrawTarget, translation: Vector2D) {
-        let translation = translation + self.relative_translation();
-        self.body.draw(dt, translation);
-        self.lt.draw(dt, translation);
-        self.rt.draw(dt, translation);
-        self.lb.draw(dt, translation);
-        self.rb.draw(dt, translation);
-        self.logo.draw(dt, translation);
-        self.left_mirror.draw(dt, translation);
-        self.right_mirror.draw(dt, translation);
-    }
-}
-
-trait Map: View{
-    fn car(&self) -> Car;
-}
-
-trait View {
-    // 返回该View相对父View的translation
-    fn relative_translation(&self) -> Vector2D;
-
-    // translation: 父View的translation
-    fn draw(&self, dt: &mut DrawTarget, translation: Vector2D);
-}
-
-
-fn main() {
-    let font = font_kit::font::Font::from_path("C:\\Windows\\Fonts\\Deng.ttf", 0)
-        .unwrap();
+    if !chinese {
+        i18n::set_language(Language::English);
+    }
+    let exam_items = ExamSession::items_from_json(config.get("exam")).unwrap_or_else(|err| {
+        println!("{}", i18n::format(Message::ExamConfigInvalid, &[&err]));
+        MapKind::ALL.to_vec()
+    });
+    // 练习记录按学员保存, 配置文件的"stats": {"path": "stats.json", "trainee": "张三"}
+    // --trainee name: 当前练车的学员, 优先于配置文件
+    let stats_config = config.get("stats");
+    let mut trainee = args.iter().position(|arg| arg == "--trainee").and_then(|i| args.get(i+1)).map(|name| name.as_str())
+        .or_else(|| stats_config.and_then(|stats| stats.get("trainee")).and_then(json::Value::as_str))
+        .unwrap_or(DEFAULT_TRAINEE).to_string();
+    let stats_path = stats_config.and_then(|stats| stats.get("path")).and_then(json::Value::as_str).unwrap_or(STATS_PATH);
+    let mut stats = StatsStore::open(stats_path).unwrap_or_else(|err| {
+        println!("{}", i18n::format(Message::StatsFileInvalid, &[&stats_path, &err]));
+        StatsStore::recover(stats_path)
+    });
+    // 统计画面上列出有记录的学员和当前学员, 教练可以不重启直接切换
+    let mut trainee_names: Vec<String> = stats.trainees().map(str::to_string).collect();
+    if !trainee_names.contains(&trainee) {
+        trainee_names.push(trainee.clone());
+    }
+    let mut tracker = AttemptTracker::new();
+    // 配置了"device"时用手柄/方向盘或脚本回放的虚拟设备控制方向和踏板
+    let mut device = config.get("device").and_then(|device| match DeviceInput::from_json(device) {
+        Ok(device) => Some(device),
+        Err(err) => {
+            println!("invalid device config, using the keyboard: {}", err);
+            None
+        },
+    });
+    // 用设备控制时的档位, 前进/后退键挂D/R档
+    let mut gear = Gear::Drive;
     let mut dt = DrawTarget::new((WINDOW_WIDTH*SCALE) as i32, (WINDOW_HEIGHT*SCALE) as i32);
-    let mut map: Box<dyn Map> = Box::new(BackParking::new());
-    let mut car = map.car();
-    let mut window = Window::new("Car-Simulation", 
-    (WINDOW_WIDTH*SCALE) as usize, (WINDOW_HEIGHT*SCALE) as usize, WindowOptions {
+    // 车的尺寸和画面比例尺, 可以在车辆设置面板里改
+    let mut spec = vehicle.spec();
+    let mut scale = SCALE;
+    let mut sim = Sim::new(MapKind::BackParking.build(spec));
+    sim.dynamics = dynamics;
+    sim.steering = Some(SteeringActuator::new(SteeringConfig::default()));
+    // --fullscreen WIDTHxHEIGHT: 用铺满屏幕、无边框、置顶的窗口当全屏
+    // minifb 0.19没有全屏接口, 也拿不到屏幕分辨率, 所以屏幕大小要在命令行里给出
+    let fullscreen = args.iter().position(|arg| arg == "--fullscreen").and_then(|i| {
+        let size = args.get(i+1).and_then(|size| size.split_once('x'))
+            .and_then(|(width, height)| Some((width.parse::<usize>().ok()?, height.parse::<usize>().ok()?)));
+        if size.is_none() {
+            println!("{}", i18n::tr(Message::FullscreenSize));
+        }
+        size
+    });
+    let (window_width, window_height) = fullscreen.unwrap_or(((WINDOW_WIDTH*SCALE) as usize, (WINDOW_HEIGHT*SCALE) as usize));
+    let mut window = Window::new("Car-Simulation", window_width, window_height, WindowOptions {
+                                    borderless: fullscreen.is_some(),
+                                    topmost: fullscreen.is_some(),
+                                    resize: true,
+                                    scale_mode: ScaleMode::UpperLeft,
                                     ..WindowOptions::default()
                                 }).unwrap();
-    let size = window.get_size();
-    let back_parking_button = Button::new(
-        pixel2real((75., 50.).into()).into(), 100./SCALE, 50./SCALE, 
-    &|| Box::new(BackParking::new()), "倒车入库", &font);
-    let parallel_parking_button = Button::new(
-        pixel2real((75., 125.).into()).into(), 100./SCALE, 50./SCALE,
-        &|| Box::new(ParallelParking::new()), "侧方停车", &font);
-    let right_angle_button = Button::new(
-        pixel2real((75., 200.).into()).into(), 100./SCALE, 50./SCALE, 
-        &|| Box::new(RightAngleTurn::new()), "直角转弯", &font);
-    let buttons = vec![back_parking_button, parallel_parking_button, right_angle_button];
+    if fullscreen.is_some() {
+        window.set_position(0, 0);
+    }
+    let mut layout = Layout::new(window.get_size());
+    // 倒车雷达指示灯和仪表排在菜单下面
+    let mut menu = build_menu();
+    let (hud_center, hud_backdrop) = layout.hud_center(menu.bottom());
+    let mut hud = Hud::new(pixel2real(hud_center.into()), hud_backdrop, &font);
+    // 车辆设置面板, 点菜单里的按钮打开, 盖在地图右上角, 改动立即作用到当前的车和考场
+    let default_spec = vehicle.spec();
+    let mut settings = Panel::new(layout.width - SETTINGS_WIDTH - MENU_PADDING, MENU_PADDING, SETTINGS_WIDTH);
+    settings.set_background(SolidSource::from_unpremultiplied_argb(0xff, 0x30, 0x30, 0x30));
+    settings.add_slider("speed", Message::Speed, 1., 20., max_speed);
+    settings.add_slider("lock_turns", Message::LockTurns, 1.5, 6., spec.lock_turns);
+    settings.add_slider("turning_radius", Message::TurningRadius,
+        default_spec.turning_radius*0.6, default_spec.turning_radius*1.6, spec.turning_radius);
+    settings.add_slider("track_width", Message::TrackWidth,
+        default_spec.track_width*0.8, default_spec.track_width*1.2, spec.track_width);
+    settings.add_slider("front_overhang", Message::FrontOverhang, 0.3, default_spec.front_overhang*2., spec.front_overhang);
+    settings.add_slider("rear_overhang", Message::RearOverhang, 0.3, default_spec.rear_overhang*2., spec.rear_overhang);
+    settings.add_slider("scale", Message::Scale, 15., 60., scale);
+    settings.add_button("defaults", Message::Defaults);
+    let mut show_settings = false;
+    // 拖滑块时只改spec, 松开鼠标再重建考场和车
+    let mut spec_changed = false;
+    // 考试模式, 再点一次菜单里的按钮结束
+    let mut session: Option<ExamSession> = None;
+    let mut show_stats = false;
+    let selected = trainee_names.iter().position(|name| *name == trainee).unwrap_or(0);
+    let mut trainee_list = stats::trainee_list(&trainee_names, selected, layout.viewport());
 
     let mut fps_monitor_last_time = SystemTime::now();
     let mut frames = 0;
@@ -564,36 +331,350 @@ fn main() {
         last_time = SystemTime::now();
         res.as_secs_f32()
     };
+    // 以下是默认按键, 可以在配置文件的"keys"里改, 操作名见input.rs
+    // R: 开始/停止录制轨迹, 第一次录制的作为参考路径, 之后的录制与参考路径比较
+    // P/O: 用pure pursuit/Stanley自动跟踪参考路径
+    let mut recorder: Option<Recorder> = None;
+    let mut reference: Option<controller::Path> = None;
+    let mut autopilot: Option<Box<dyn Controller>> = None;
+    // N: 在起点放一辆新车并切换过去, Tab: 切换操控的车, T: 挂上/摘下挂车
+    // U: 显示/隐藏倒车雷达, L: 开启/关闭激光雷达
+    let mut show_ultrasonic = true;
+    // C: 开启/关闭松手回正
+    // F: 开启/关闭后轮转向, 开启后画出转弯时车身扫过的区域
+    // V: 切换360°全景影像
+    let surround_view = SurroundView::new();
+    let mut show_surround_view = false;
+    let mut scene = DrawTarget::new(dt.width(), dt.height());
+    // 在仪表的方向盘上按住鼠标左键拖动来转方向盘
+    let mut wheel_drag: Option<WheelDrag> = None;
+    let mut mouse_was_down = false;
     window.limit_update_rate(None);
     while window.is_open() {
-        if window.get_mouse_down(MouseButton::Left) {
-            let pixel_point: Point = window.get_mouse_pos(minifb::MouseMode::Clamp).unwrap().into();
-            let point: Point = pixel2real(pixel_point).into();
-            for button in buttons.iter() {
-                if button.in_range(point) {
-                    map = button.on_click();
-                    car = map.car();
-                    break;
+        let frame_time = elapsed_time();
+        // 窗口大小变了就按新大小重新建画布, 设置面板贴着右边
+        let (width, height) = window.get_size();
+        if Layout::new((width.max(1), height.max(1))) != layout {
+            layout = Layout::new((width.max(1), height.max(1)));
+            dt = DrawTarget::new(layout.width as i32, layout.height as i32);
+            scene = DrawTarget::new(dt.width(), dt.height());
+            settings.set_left(layout.width - SETTINGS_WIDTH - MENU_PADDING);
+            trainee_list.set_left(stats::trainee_list_left(layout.viewport()));
+            let (hud_center, hud_backdrop) = layout.hud_center(menu.bottom());
+            hud.set_position(pixel2real(hud_center.into()), hud_backdrop);
+        }
+        let input = InputState::poll(&window, &bindings);
+        let mouse = window.get_mouse_pos(minifb::MouseMode::Clamp).unwrap_or((0., 0.));
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        let events = menu.update(mouse, mouse_down);
+        let settings_events = if show_settings {settings.update(mouse, mouse_down)} else {vec![]};
+        let trainee_events = if show_stats {trainee_list.update(mouse, mouse_down)} else {vec![]};
+        if !mouse_down {
+            wheel_drag = None;
+        } else if let Some(drag) = wheel_drag.as_mut() {
+            // 拖的是画出来的方向盘, 按实际圈数换算成steer_angle
+            let turns = hud.drag(drag, mouse.into());
+            sim.steer_to(sim.steer_target() + turns*sim.car.spec.lock_turns/WHEEL_TURNS_TO_LOCK);
+        } else if !mouse_was_down {
+            wheel_drag = hud.grab(mouse.into());
+        }
+        mouse_was_down = mouse_down;
+        // 菜单和远程控制换地图、重置都记在这里, 收完远程命令后一起处理
+        let mut load_map = None;
+        let mut reset = false;
+        for event in events.iter() {
+            match *event {
+                UiEvent::Selected("map", i) => load_map = Some(MapKind::ALL[i]),
+                UiEvent::Clicked("exam") => {
+                    session = match session {
+                        Some(_) => None,
+                        None => {
+                            let exam = ExamSession::new(exam_items.clone());
+                            load_course(&mut sim, exam.item(), spec, &mut recorder, &mut reference, &mut autopilot);
+                            Some(exam)
+                        },
+                    };
+                },
+                UiEvent::Changed("steering_rate", rate) => if let Some(steering) = sim.steering.as_mut() {
+                    steering.config.max_rate = rate;
+                },
+                UiEvent::Clicked("stats") => show_stats = !show_stats,
+                UiEvent::Clicked("settings") => show_settings = !show_settings,
+                UiEvent::Clicked("reset") => reset = true,
+                // 没有中文字体时不能切到中文
+                UiEvent::Clicked("language") if chinese => i18n::set_language(i18n::language().next()),
+                _ => {},
+            }
+        }
+        for event in trainee_events.iter() {
+            if let UiEvent::Selected("trainee", i) = *event {
+                trainee = trainee_names[i].clone();
+                println!("{}", i18n::format(Message::TraineeSelected, &[&trainee]));
+            }
+        }
+        for event in settings_events.iter() {
+            match *event {
+                UiEvent::Changed("speed", value) => max_speed = value,
+                UiEvent::Changed("scale", value) => scale = value,
+                UiEvent::Changed(id, value) => {
+                    match id {
+                        "lock_turns" => spec.lock_turns = value,
+                        "turning_radius" => spec.turning_radius = value,
+                        "track_width" => spec.track_width = value,
+                        "front_overhang" => spec.front_overhang = value,
+                        "rear_overhang" => spec.rear_overhang = value,
+                        _ => {},
+                    }
+                    spec.clamp();
+                    spec_changed = true;
+                },
+                UiEvent::Clicked("defaults") => {
+                    spec = default_spec;
+                    max_speed = default_speed;
+                    scale = SCALE;
+                    spec_changed = true;
+                },
+                _ => {},
+            }
+        }
+        if spec_changed && !mouse_down {
+            sim.set_spec(spec);
+            spec_changed = false;
+        }
+        let world = layout.world_transform(scale);
+        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0x00, 0x00, 0x00));
+        layout.begin_world(&mut dt, &world);
+        let mut painter = Painter::new(&mut dt);
+        sim.map.draw(&mut painter, (0., 0.).into());
+        for obstacle in sim.map.obstacles().iter() {
+            obstacle.draw(&mut painter, (0., 0.).into());
+        }
+        for agent in sim.agents.iter() {
+            agent.draw(&mut painter, (0., 0.).into());
+        }
+        layout.end_world(&mut dt);
+        if input.pressed(Action::Record) {
+            match recorder.take() {
+                Some(r) => {
+                    let trajectory = r.finish();
+                    match &reference {
+                        Some(reference) => {
+                            let stats = controller::compare(reference, &trajectory);
+                            println!("deviation from reference: mean = {:.3}m, rms = {:.3}m, max = {:.3}m",
+                                stats.mean, stats.rms, stats.max);
+                        },
+                        None => reference = Some(trajectory),
+                    }
+                },
+                None => recorder = Some(Recorder::new(RECORD_SPACING)),
+            }
+        }
+        if let Some(on) = switched(&events, "ultrasonic", input.pressed(Action::Ultrasonic), show_ultrasonic) {
+            show_ultrasonic = on;
+        }
+        if input.pressed(Action::AddCar) {
+            if sim.add_car() {
+                autopilot = None;
+            } else {
+                println!("{}", i18n::tr(Message::StartOccupied));
+            }
+        }
+        if input.pressed(Action::SwitchCar) {
+            autopilot = sim.switch_car(autopilot.take());
+        }
+        if input.pressed(Action::Trailer) {
+            let config = match sim.car.trailer {
+                Some(_) => None,
+                None => Some(TrailerConfig::default()),
+            };
+            sim.car.set_trailer(config);
+        }
+        if let Some(steering) = sim.steering.as_mut() {
+            let on = steering.config.self_align > 0.;
+            if let Some(on) = switched(&events, "self_align", input.pressed(Action::SelfAlign), on) {
+                steering.config.self_align = if on {SELF_ALIGN} else {0.};
+            }
+        }
+        if let Some(on) = switched(&events, "rear_steer", input.pressed(Action::RearSteer), sim.car.rear_steer.is_some()) {
+            let before = sim.car.min_turning_radius();
+            sim.car.set_rear_steer(if on {Some(RearSteerConfig::default())} else {None});
+            println!("{}", i18n::format(if on {Message::RearSteerOn} else {Message::RearSteerOff},
+                &[&format!("{:.2}", before), &format!("{:.2}", sim.car.min_turning_radius())]));
+        }
+        if let Some(on) = switched(&events, "surround_view", input.pressed(Action::SurroundView), show_surround_view) {
+            show_surround_view = on;
+        }
+        if input.pressed(Action::Lidar) {
+            sim.lidar = match sim.lidar {
+                Some(_) => None,
+                None => Some(Lidar::new(LidarConfig::default(), 0)),
+            };
+        }
+        if let Some(path) = &reference {
+            if input.pressed(Action::PurePursuit) {
+                autopilot = Some(Box::new(PurePursuit::new(path.clone(), LOOKAHEAD)));
+            } else if input.pressed(Action::Stanley) {
+                autopilot = Some(Box::new(Stanley::new(path.clone(), STANLEY_GAIN, 1.)));
+            }
+        }
+        if let Some(server) = remote.as_mut() {
+            for command in server.poll() {
+                match command {
+                    RemoteCommand::Steer(angle) => sim.steer_to(angle),
+                    RemoteCommand::Reset => reset = true,
+                    RemoteCommand::LoadMap(kind) => load_map = Some(kind),
+                    RemoteCommand::Throttle(_) | RemoteCommand::Gear(_) => {},
                 }
             }
         }
-        map.draw(&mut dt, (0., 0.).into());
-        for button in buttons.iter() {
-            button.draw(&mut dt, (0., 0.).into());
-        }
-        let distance = elapsed_time() * SPEED;
-        if window.is_key_down(Key::Up) {
-            car.forward(distance);
-        } else if window.is_key_down(Key::Down) {
-            car.forward(-distance);
-        } else if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
-            car.left_steer();
-        } else if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
-            car.right_steer();
-        }
-        car.draw(&mut dt, (0., 0.).into());
-        window.update_with_buffer(dt.get_data(), size.0, size.1).unwrap();
+        if let Some(kind) = load_map {
+            load_course(&mut sim, kind, spec, &mut recorder, &mut reference, &mut autopilot);
+        } else if reset {
+            sim.reset();
+        }
+        if load_map.is_some() || reset {
+            autopilot = None;
+            session = None;
+        }
+        sim.speed = 0.;
+        if let Some((gear, throttle)) = remote.as_ref().and_then(RemoteServer::drive) {
+            sim.speed = gear.sign()*throttle*top_speed(gear, max_speed);
+        } else if let Some(controller) = autopilot.as_mut() {
+            let command = controller.control(&sim.car);
+            sim.steer_to(command.steer_angle);
+            sim.speed = command.speed;
+            if controller.finished() {
+                let error = controller.error();
+                println!("{}", i18n::format(Message::AutopilotFinished,
+                    &[&format!("{:.3}", error.cross_track), &format!("{:.3}", error.heading)]));
+                autopilot = None;
+            }
+        } else if let Some(device) = device.as_mut() {
+            if input.pressed(Action::Forward) {
+                gear = Gear::Drive;
+            } else if input.pressed(Action::Backward) {
+                gear = Gear::Reverse;
+            }
+            let analog = device.poll(frame_time);
+            sim.steer_to(analog.steering*sim.car.spec.lock_turns);
+            sim.speed = gear.sign()*analog.drive()*top_speed(gear, max_speed);
+        } else {
+            // 油门和方向互不影响, 可以边走边打方向; 前进后退同时按住时不动
+            if let Some(drive) = input.drive() {
+                let gear = if drive > 0. {Gear::Drive} else {Gear::Reverse};
+                sim.speed = gear.sign()*top_speed(gear, max_speed);
+            }
+            if input.steer() != 0. {
+                sim.steer_by(input.steer());
+            }
+        }
+        // 考试播报和显示结果时车不能动
+        if session.as_ref().is_some_and(|session| !session.driving()) {
+            sim.speed = 0.;
+        }
+        // 远程客户端转过方向盘和自动驾驶时一直握着方向盘, 键盘操作时不按方向键就是松手
+        let held = remote.as_ref().is_some_and(RemoteServer::steering) || autopilot.is_some() || wheel_drag.is_some() || device.is_some()
+            || input.steering();
+        if let Some(steering) = sim.steering.as_mut() {
+            steering.held = held;
+        }
+        let mut exam_events = sim.step(frame_time);
+        exam_events.extend(session.as_ref().and_then(|session| session.check_time(&mut sim)));
+        for &event in exam_events.iter() {
+            println!("{}", i18n::format(Message::ExamEventLog,
+                &[&i18n::tr(event.into()), &event.deduction(), &sim.exam.score()]));
+            if let Some(server) = remote.as_mut() {
+                server.send_event(event, &sim);
+            }
+        }
+        if let Some(attempt) = tracker.update(&sim, &exam_events) {
+            match stats.add(&trainee, attempt) {
+                Ok(()) => println!("{}", i18n::format(Message::PracticeSaved, &[&trainee])),
+                Err(err) => println!("{}", i18n::format(Message::PracticeSaveFailed, &[&stats_path, &err])),
+            }
+        }
+        if let Some(kind) = session.as_mut().and_then(|session| session.update(&sim, &exam_events, frame_time)) {
+            load_course(&mut sim, kind, spec, &mut recorder, &mut reference, &mut autopilot);
+        }
+        if let Some(server) = remote.as_mut() {
+            server.send_state(&sim);
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.record(&sim.car, frame_time);
+        }
+        layout.begin_world(&mut dt, &world);
+        let mut painter = Painter::new(&mut dt);
+        for state in sim.cars.iter() {
+            state.car.draw(&mut painter, (0., 0.).into());
+        }
+        sim.car.draw(&mut painter, (0., 0.).into());
+        if let Some(lidar) = &sim.lidar {
+            lidar.draw(&mut painter, (0., 0.).into());
+        }
+        if show_ultrasonic {
+            sim.ultrasonic.draw(&mut painter, (0., 0.).into());
+        }
+        layout.end_world(&mut dt);
+
+        // 键盘和远程控制改的状态同步到菜单上
+        menu.set_selected("map", MapKind::ALL.iter().position(|&kind| kind == sim.map.kind()).unwrap());
+        menu.set_label("score", sim.exam.score().to_string());
+        menu.set_toggle("ultrasonic", show_ultrasonic);
+        menu.set_toggle("surround_view", show_surround_view);
+        menu.set_toggle("rear_steer", sim.car.rear_steer.is_some());
+        if let Some(steering) = &sim.steering {
+            menu.set_toggle("self_align", steering.config.self_align > 0.);
+            menu.set_value("steering_rate", steering.config.max_rate);
+        }
+        menu.draw(&mut dt, &font);
+        hud.draw(&mut dt, &sim.car, sim.speed, sim.steering.as_ref());
+        if show_ultrasonic {
+            hud::draw_sonar_indicator(&mut dt, &font, &sim.ultrasonic, hud.indicator(), sim.time);
+        }
+        if show_surround_view {
+            scene.get_data_mut().copy_from_slice(dt.get_data());
+            surround_view.draw(&mut dt, &scene, layout.viewport(), &world, &sim.car, sim.speed);
+        }
+        if let Some(session) = &session {
+            session.draw(&mut dt, &font, layout.viewport());
+        }
+        if show_stats {
+            stats::draw(&mut dt, &font, &trainee, stats.attempts(&trainee), layout.viewport());
+            trainee_list.draw(&mut dt, &font);
+        }
+        if show_settings {
+            settings.set_value("speed", max_speed);
+            settings.set_value("lock_turns", spec.lock_turns);
+            settings.set_value("turning_radius", spec.turning_radius);
+            settings.set_value("track_width", spec.track_width);
+            settings.set_value("front_overhang", spec.front_overhang);
+            settings.set_value("rear_overhang", spec.rear_overhang);
+            settings.set_value("scale", scale);
+            settings.draw(&mut dt, &font);
+        }
+        window.update_with_buffer(dt.get_data(), dt.width() as usize, dt.height() as usize).unwrap();
 
         fps_monitor();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Layout, build_menu, hud};
+
+    #[test]
+    fn hud_fits_window() {
+        let menu = build_menu();
+        // 默认窗口里仪表排在菜单下面, 最下面的档位不超出窗口
+        let ((x, y), backdrop) = Layout::new((950, 800)).hud_center(menu.bottom());
+        assert!(!backdrop);
+        assert!(y - hud::TOP >= menu.bottom() && y + hud::BOTTOM <= 800.);
+        assert!(x - hud::HALF_WIDTH >= 0.);
+        // 矮窗口里贴着底边画在地图视口里, 不和菜单重叠
+        let layout = Layout::new((950, 600));
+        let ((x, y), backdrop) = layout.hud_center(menu.bottom());
+        assert!(backdrop);
+        assert!(y - hud::TOP >= 0. && y + hud::BOTTOM <= 600.);
+        assert!(x - hud::HALF_WIDTH >= layout.viewport().0);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Canvas, Color, Point, Rect, View, MENU_WIDTH, new_rotation_matrix, geometry::Segment, linear_algebra::Vector2D};
+
+// 圆形障碍物的轮廓用正多边形近似, 供传感器的射线检测
+const CIRCLE_SIDES: usize = 16;
+const CONE_RADIUS: f32 = 0.2;
+const POLE_RADIUS: f32 = 0.1;
+const PEDESTRIAN_RADIUS: f32 = 0.3;
+
+// 车身碰到即不合格的障碍物, 也用来表示移动的交通参与者(见agent.rs)
+#[derive(Clone, Copy)]
+pub enum Obstacle {
+    // 锥桶
+    Cone(Point),
+    // 立柱
+    Pole(Point),
+    // 停着的车, 用车身大小的有向矩形表示
+    Vehicle(Rect),
+    Pedestrian(Point),
+}
+
+impl Obstacle {
+    // 停在origin处、车头朝angle方向的车, angle同Car::new
+    pub fn vehicle(origin: Point, width: f32, height: f32, angle: f32) -> Self {
+        let mut body = Rect::new(origin, width, height, Some(Color::rgb(0x90, 0x90, 0x98)));
+        body.rotate_self(new_rotation_matrix(angle));
+        Obstacle::Vehicle(body)
+    }
+
+    fn circle(&self) -> Option<(Point, f32)> {
+        match self {
+            Obstacle::Cone(center) => Some((*center, CONE_RADIUS)),
+            Obstacle::Pole(center) => Some((*center, POLE_RADIUS)),
+            Obstacle::Pedestrian(center) => Some((*center, PEDESTRIAN_RADIUS)),
+            Obstacle::Vehicle(_) => None,
+        }
+    }
+
+    pub fn outline(&self) -> Vec<Segment> {
+        match self {
+            Obstacle::Vehicle(body) => body.edges().to_vec(),
+            _ => {
+                let (center, radius) = self.circle().unwrap();
+                let vertex = |i: usize| {
+                    let angle = 2.*std::f32::consts::PI*(i as f32)/(CIRCLE_SIDES as f32);
+                    center + radius*Vector2D::new_from_x_and_y(angle.cos(), angle.sin())
+                };
+                (0..CIRCLE_SIDES).map(|i| Segment::new(vertex(i), vertex(i+1))).collect()
+            },
+        }
+    }
+
+    // body: 车身或挂车车厢
+    pub fn collides(&self, body: &Rect) -> bool {
+        let edges = body.edges();
+        match self {
+            Obstacle::Vehicle(other) => {
+                let other_edges = other.edges();
+                edges.iter().any(|e| other_edges.iter().any(|o| e.intersects(o)))
+                    || body.contains(other.origin)
+                    || other.contains(body.origin)
+            },
+            _ => {
+                let (center, radius) = self.circle().unwrap();
+                body.contains(center) || edges.iter().any(|e| e.distance_to(center) < radius)
+            },
+        }
+    }
+}
+
+impl View for Obstacle {
+    fn relative_translation(&self) -> Vector2D {
+        (MENU_WIDTH, 0.).into()
+    }
+
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let translation = translation + self.relative_translation();
+        match self {
+            Obstacle::Vehicle(body) => {
+                body.draw(canvas, translation);
+                // 前挡风玻璃, 区分车头车尾
+                let front = body.origin + body.rotation_matrix*Vector2D::new_from_x_and_y(0., body.height/4.);
+                let mut windshield = Rect::new(front, body.width*0.8, body.height/6.,
+                    Some(Color::rgb(0x40, 0x48, 0x58)));
+                windshield.rotate_self(body.rotation_matrix);
+                windshield.draw(canvas, translation);
+            },
+            _ => {
+                let (center, radius) = self.circle().unwrap();
+                let color = match self {
+                    Obstacle::Cone(_) => Color::rgb(0xff, 0x70, 0x00),
+                    Obstacle::Pedestrian(_) => Color::rgb(0xd0, 0x30, 0x60),
+                    _ => Color::rgb(0xa0, 0xa0, 0xa0),
+                };
+                canvas.fill_circle(center + translation, radius, color);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CIRCLE_SIDES, CONE_RADIUS, Obstacle};
+    use crate::{Rect, point2};
+
+    // 2m x 4m, 中心在原点
+    fn body() -> Rect {
+        Rect::new(point2(0., 0.), 2., 4., None)
+    }
+
+    #[test]
+    fn cone() {
+        let body = body();
+        assert!(Obstacle::Cone(point2(0., 0.)).collides(&body));
+        // 圆心在车外, 半径够到车身
+        assert!(Obstacle::Cone(point2(1. + CONE_RADIUS*0.9, 0.)).collides(&body));
+        assert!(!Obstacle::Cone(point2(1. + CONE_RADIUS*1.1, 0.)).collides(&body));
+        assert!(!Obstacle::Pole(point2(0., 2.15)).collides(&body));
+        assert!(Obstacle::Pole(point2(0., 2.05)).collides(&body));
+        assert_eq!(Obstacle::Cone(point2(3., 3.)).outline().len(), CIRCLE_SIDES);
+    }
+
+    #[test]
+    fn vehicle() {
+        let body = body();
+        // 边相交
+        assert!(Obstacle::vehicle(point2(1.5, 3.), 2., 4., 0.).collides(&body));
+        // 一个完全在另一个里面, 边不相交
+        assert!(Obstacle::vehicle(point2(0., 0.), 1., 1., 0.).collides(&body));
+        assert!(Obstacle::vehicle(point2(0., 0.), 10., 10., 0.).collides(&body));
+        // 并排停着, 中间留0.2m
+        assert!(!Obstacle::vehicle(point2(2.2, 0.), 2., 4., 0.).collides(&body));
+        // 转45°以后角伸进来
+        assert!(!Obstacle::vehicle(point2(2.3, 0.), 2., 2., 0.).collides(&body));
+        assert!(Obstacle::vehicle(point2(2.3, 0.), 2., 2., std::f32::consts::PI/4.).collides(&body));
+        assert_eq!(Obstacle::vehicle(point2(5., 5.), 2., 4., 0.).outline().len(), 4);
+    }
+}
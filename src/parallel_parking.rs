@@ -1,27 +1,32 @@
-use raqote::{DrawTarget, SolidSource};
+use crate::{Color, Canvas, CAR_HEIGHT, CAR_WIDTH, Rect, point2, Car, geometry::Segment, obstacle::Obstacle, vehicle::VehicleSpec, Map, MapKind, WINDOW_WIDTH, WINDOW_HEIGHT, MENU_WIDTH, View};
 
-use crate::{CAR_HEIGHT, CAR_WIDTH, Rect, point2, Car, Map, WINDOW_WIDTH, WINDOW_HEIGHT, MENU_WIDTH, View};
-
-const ROAD_WIDTH: f32 = CAR_WIDTH * 3.;
+// 小型汽车的车位尺寸, 大车按车长和车宽等比例放大
 const PARKING_LENGTH: f32 = 6.7;
 const PARKING_WIDTH: f32 = 3.0;
+// 车位前后停着的车离车位线的距离
+const PARKED_CAR_GAP: f32 = 0.3;
 const MAP_WIDTH: f32 = WINDOW_WIDTH - MENU_WIDTH;
 const MAP_HEIGHT: f32 = WINDOW_HEIGHT;
 
 
 pub struct ParallelParking {
+    spec: VehicleSpec,
     road: Rect,
     parking_space: Rect,
 }
 
 impl ParallelParking {
-    pub fn new() -> Self {
-        let color = SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff);
-        let road = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT/2.), ROAD_WIDTH, MAP_HEIGHT, Some(color));
+    pub fn new(spec: VehicleSpec) -> Self {
+        let color = Color::rgb(0xff, 0xff, 0xff);
+        let road_width = spec.width*3.;
+        let parking_length = PARKING_LENGTH*spec.length/CAR_HEIGHT;
+        let parking_width = PARKING_WIDTH*spec.width/CAR_WIDTH;
+        let road = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT/2.), road_width, MAP_HEIGHT, Some(color));
         let parking_space = Rect::new(
-            point2(road.origin.x+ROAD_WIDTH/2.+PARKING_WIDTH/2., road.origin.y),
-            PARKING_WIDTH, PARKING_LENGTH, Some(color));
+            point2(road.origin.x+road_width/2.+parking_width/2., road.origin.y),
+            parking_width, parking_length, Some(color));
         ParallelParking {
+            spec,
             road,
             parking_space,
         }
@@ -29,8 +34,39 @@ impl ParallelParking {
 }
 
 impl Map for ParallelParking {
+    fn kind(&self) -> MapKind {
+        MapKind::ParallelParking
+    }
+
     fn car(&self) -> Car {
-        Car::new(point2(self.road.origin.x, CAR_HEIGHT), 0.)
+        Car::new(self.spec, point2(self.road.origin.x, self.spec.length), 0.)
+    }
+
+    fn lines(&self) -> Vec<Segment> {
+        let [_, _, _, left] = self.road.edges();
+        let [top, right, bottom, _] = self.parking_space.edges();
+        vec![
+            left,
+            Segment::new(self.road.rt(), self.parking_space.lt()),
+            Segment::new(self.parking_space.lb(), self.road.rb()),
+            top,
+            right,
+            bottom,
+        ]
+    }
+
+    fn goal(&self) -> Rect {
+        self.parking_space
+    }
+
+    // 车位前后各停一辆车
+    fn obstacles(&self) -> Vec<Obstacle> {
+        let offset = self.parking_space.height/2. + PARKED_CAR_GAP + CAR_HEIGHT/2.;
+        let origin = self.parking_space.origin;
+        vec![
+            Obstacle::vehicle(point2(origin.x, origin.y+offset), CAR_WIDTH, CAR_HEIGHT, 0.),
+            Obstacle::vehicle(point2(origin.x, origin.y-offset), CAR_WIDTH, CAR_HEIGHT, 0.),
+        ]
     }
 }
 
@@ -39,10 +75,10 @@ impl View for ParallelParking {
         (MENU_WIDTH, 0.).into()
     }
 
-    fn draw(&self, dt: &mut DrawTarget, translation: crate::linear_algebra::Vector2D) {
+    fn draw(&self, canvas: &mut dyn Canvas, translation: crate::linear_algebra::Vector2D) {
         let translation = self.relative_translation() + translation;
-        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0x00, 0x00, 0x00));
-        self.road.draw(dt, translation);
-        self.parking_space.draw(dt, translation);
+        canvas.clear(Color::rgb(0x00, 0x00, 0x00));
+        self.road.draw(canvas, translation);
+        self.parking_space.draw(canvas, translation);
     }
 }
\ No newline at end of file
@@ -0,0 +1,348 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{MapKind, Point, distance_of, exam::{ExamEvent, ExamStatus}, json::Value, sim::{Gear, Sim}};
+
+// 一次练习: 从加载地图或重置开始, 到考试结果出来为止
+#[derive(Clone, PartialEq, Debug)]
+pub struct Attempt {
+    pub map: MapKind,
+    // 结束时的unix时间戳(s)
+    pub time: u64,
+    pub duration: f32,
+    pub passed: bool,
+    pub score: i32,
+    pub deductions: Vec<ExamEvent>,
+    // 轨迹概况: 后轴中心行驶的距离(m)和前进后退切换的次数
+    pub distance: f32,
+    pub reversals: i32,
+}
+
+impl Attempt {
+    pub fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("map".to_string(), self.map.name().into()),
+            ("time".to_string(), Value::Number(self.time as f64)),
+            ("duration".to_string(), self.duration.into()),
+            ("passed".to_string(), self.passed.into()),
+            ("score".to_string(), self.score.into()),
+            ("deductions".to_string(), Value::Array(self.deductions.iter().map(|event| event.name().into()).collect())),
+            ("distance".to_string(), self.distance.into()),
+            ("reversals".to_string(), self.reversals.into()),
+        ])
+    }
+
+    pub fn from_json(value: &Value) -> Result<Attempt, String> {
+        let number = |key: &str| match value.get(key) {
+            Some(Value::Number(n)) => Ok(*n),
+            _ => Err(format!("{} must be a number", key)),
+        };
+        let map = value.get("map").and_then(Value::as_str).and_then(MapKind::from_name).ok_or("unknown map")?;
+        let deductions = value.get("deductions").and_then(Value::as_array).ok_or("deductions must be an array")?
+            .iter().map(|event| event.as_str().and_then(ExamEvent::from_name).ok_or(format!("unknown event: {:?}", event)))
+            .collect::<Result<_, _>>()?;
+        Ok(Attempt {
+            map,
+            time: number("time")? as u64,
+            duration: number("duration")? as f32,
+            passed: value.get("passed").and_then(Value::as_bool).ok_or("passed must be a boolean")?,
+            score: number("score")? as i32,
+            deductions,
+            distance: number("distance")? as f32,
+            reversals: number("reversals")? as i32,
+        })
+    }
+}
+
+// 练习记录文件的内容: {"学员名": [记录, ...], ...}, 学员按第一次练习的顺序
+pub fn records_to_json(trainees: &[(String, Vec<Attempt>)]) -> Value {
+    Value::Object(trainees.iter()
+        .map(|(name, attempts)| (name.clone(), Value::Array(attempts.iter().map(Attempt::to_json).collect())))
+        .collect())
+}
+
+pub fn records_from_json(value: &Value) -> Result<Vec<(String, Vec<Attempt>)>, String> {
+    let fields = match value {
+        Value::Object(fields) => fields,
+        _ => return Err("stats file must be an object".to_string()),
+    };
+    let mut trainees = vec![];
+    for (name, attempts) in fields.iter() {
+        let attempts = attempts.as_array().ok_or(format!("attempts of {} must be an array", name))?
+            .iter().map(Attempt::from_json).collect::<Result<_, _>>()?;
+        trainees.push((name.clone(), attempts));
+    }
+    Ok(trainees)
+}
+
+// 统计当前操控的车的一次练习, 考试结果出来时生成记录
+pub struct AttemptTracker {
+    // 统计的是Sim::attempt的哪一次
+    attempt: Option<u32>,
+    // 这次练习开始和上一帧的仿真时间
+    start: f32,
+    time: f32,
+    distance: f32,
+    // 上一帧后轴中心的位置, 行驶距离按它的位移累计
+    last: Option<Point>,
+    reversals: i32,
+    // 上一次车在动时的档位
+    gear: Gear,
+    deductions: Vec<ExamEvent>,
+    finished: bool,
+}
+
+impl Default for AttemptTracker {
+    fn default() -> Self {
+        AttemptTracker::new()
+    }
+}
+
+impl AttemptTracker {
+    pub fn new() -> Self {
+        AttemptTracker {
+            attempt: None,
+            start: 0.,
+            time: 0.,
+            distance: 0.,
+            last: None,
+            reversals: 0,
+            gear: Gear::Neutral,
+            deductions: vec![],
+            finished: false,
+        }
+    }
+
+    // 每帧在Sim::step之后调用, events是这一帧的考试事件
+    // 重置、换地图或换车以后Sim::attempt会变, 这时重新开始统计
+    pub fn update(&mut self, sim: &Sim, events: &[ExamEvent]) -> Option<Attempt> {
+        if self.attempt != Some(sim.attempt) {
+            // 重置和换地图时仿真时间归零, 换车时从上一帧接着算
+            let start = if sim.time < self.time {0.} else {self.time};
+            *self = AttemptTracker {
+                attempt: Some(sim.attempt),
+                start,
+                // 换到的车已经考完了, 结果算在之前那次练习里
+                finished: sim.exam.status() != ExamStatus::Running,
+                ..AttemptTracker::new()
+            };
+        }
+        self.time = sim.time;
+        if self.finished {
+            return None;
+        }
+        let position = sim.car.back_origin();
+        self.distance += self.last.map_or(0., |last| distance_of(last, position));
+        self.last = Some(position);
+        let gear = Gear::of(sim.speed);
+        if gear != Gear::Neutral {
+            if self.gear != Gear::Neutral && gear != self.gear {
+                self.reversals += 1;
+            }
+            self.gear = gear;
+        }
+        self.deductions.extend(events.iter().filter(|event| event.deduction() > 0));
+        if sim.exam.status() == ExamStatus::Running {
+            return None;
+        }
+        self.finished = true;
+        Some(Attempt {
+            map: sim.map.kind(),
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            duration: sim.time - self.start,
+            passed: sim.exam.status() == ExamStatus::Passed,
+            score: sim.exam.score(),
+            deductions: self.deductions.clone(),
+            distance: self.distance,
+            reversals: self.reversals,
+        })
+    }
+}
+
+// 合格率(%), 没有记录时为0
+pub fn pass_rate(attempts: &[&Attempt]) -> f32 {
+    let passed = attempts.iter().filter(|attempt| attempt.passed).count();
+    100.*(passed as f32)/(attempts.len().max(1) as f32)
+}
+
+// 出现次数最多的count个扣分项, 多到少排列, 次数一样时按ExamEvent::ALL的顺序
+pub fn frequent_mistakes(attempts: &[&Attempt], count: usize) -> Vec<(ExamEvent, usize)> {
+    let mut counts: Vec<(ExamEvent, usize)> = ExamEvent::ALL.iter()
+        .map(|&event| (event, attempts.iter().map(|attempt| attempt.deductions.iter().filter(|&&e| e == event).count()).sum()))
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts.truncate(count);
+    counts
+}
+
+// 按天(UTC)分组, 天数是unix时间戳/86400, 从早到晚排列
+pub fn by_day(attempts: &[Attempt]) -> Vec<(u64, Vec<&Attempt>)> {
+    let mut days: Vec<(u64, Vec<&Attempt>)> = vec![];
+    for attempt in attempts.iter() {
+        let day = attempt.time/86400;
+        match days.iter_mut().find(|(d, _)| *d == day) {
+            Some((_, of_day)) => of_day.push(attempt),
+            None => days.push((day, vec![attempt])),
+        }
+    }
+    days.sort_by_key(|(day, _)| *day);
+    days
+}
+
+// unix时间戳 -> (月, 日), 按UTC算
+pub fn month_day(time: u64) -> (i64, i64) {
+    let z = (time/86400) as i64 + 719468;
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096)/365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2)/153;
+    let day = doy - (153*mp + 2)/5 + 1;
+    (if mp < 10 {mp + 3} else {mp - 9}, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vehicle::VehicleClass;
+
+    fn attempt(map: MapKind, time: u64, passed: bool, deductions: Vec<ExamEvent>) -> Attempt {
+        Attempt {
+            map,
+            time,
+            duration: 12.5,
+            passed,
+            score: if passed {100} else {0},
+            deductions,
+            distance: 33.25,
+            reversals: 2,
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let records = vec![
+            ("张三".to_string(), vec![
+                attempt(MapKind::RightAngleTurn, 1735603200, false, vec![ExamEvent::Stopped, ExamEvent::LineTouched]),
+                attempt(MapKind::BackParking, 1735603300, true, vec![]),
+            ]),
+            ("li".to_string(), vec![]),
+        ];
+        let text = records_to_json(&records).to_string();
+        assert_eq!(records_from_json(&Value::parse(&text).unwrap()).unwrap(), records);
+    }
+
+    #[test]
+    fn bad_records() {
+        for text in [
+            r#"[]"#,
+            r#"{"a": {}}"#,
+            r#"{"a": [{"map": "moon"}]}"#,
+            r#"{"a": [{"map": "crossing", "deductions": ["sneezed"]}]}"#,
+            r#"{"a": [{"map": "crossing", "deductions": [], "time": "now"}]}"#,
+        ].iter() {
+            assert!(records_from_json(&Value::parse(text).unwrap()).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn aggregates() {
+        let attempts = vec![
+            attempt(MapKind::BackParking, 0, false, vec![ExamEvent::LineTouched, ExamEvent::Stopped]),
+            attempt(MapKind::BackParking, 86400, true, vec![ExamEvent::Stopped]),
+            attempt(MapKind::BackParking, 86401, false, vec![ExamEvent::Collision, ExamEvent::Stopped]),
+            attempt(MapKind::BackParking, 100, true, vec![]),
+        ];
+        let all: Vec<&Attempt> = attempts.iter().collect();
+        assert_eq!(pass_rate(&all), 50.);
+        assert_eq!(pass_rate(&[]), 0.);
+        assert_eq!(frequent_mistakes(&all, 3),
+            vec![(ExamEvent::Stopped, 3), (ExamEvent::LineTouched, 1), (ExamEvent::Collision, 1)]);
+        assert_eq!(frequent_mistakes(&all, 1), vec![(ExamEvent::Stopped, 3)]);
+        assert!(frequent_mistakes(&all[3..], 3).is_empty());
+        let days = by_day(&attempts);
+        assert_eq!(days.iter().map(|(day, of_day)| (*day, of_day.len())).collect::<Vec<_>>(), vec![(0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn month_day_of_timestamps() {
+        assert_eq!(month_day(0), (1, 1));
+        // 闰年的2月29日, 闰年3月1日的最后一秒
+        assert_eq!(month_day(951782400), (2, 29));
+        assert_eq!(month_day(1709251200 + 86399), (3, 1));
+        assert_eq!(month_day(1735603200), (12, 31));
+    }
+
+    #[test]
+    fn tracker_follows_rear_axle() {
+        let mut sim = Sim::new(MapKind::RightAngleTurn.build(VehicleClass::Car.spec()));
+        let mut tracker = AttemptTracker::new();
+        tracker.update(&sim, &[]);
+        // 打着方向前进再倒回来, 后轴走的弧比前轴(车速)短
+        sim.car.set_steer_angle(1.);
+        let mut expected = 0.;
+        for &speed in [2., 2., -2., 0., -2.].iter() {
+            for _ in 0..5 {
+                let before = sim.car.back_origin();
+                sim.speed = speed;
+                let events = sim.step(0.1);
+                expected += distance_of(before, sim.car.back_origin());
+                assert!(tracker.update(&sim, &events).is_none(), "{:?}", events);
+            }
+        }
+        assert!((tracker.distance - expected).abs() < 1e-4);
+        assert!(tracker.distance > 3. && tracker.distance < 0.1*2.*20.);
+        // 中间停过一下的倒车不算再换一次
+        assert_eq!(tracker.reversals, 1);
+        // 重置以后重新统计
+        sim.reset();
+        tracker.update(&sim, &[]);
+        assert_eq!(tracker.distance, 0.);
+        assert_eq!(tracker.reversals, 0);
+    }
+
+    #[test]
+    fn tracker_restarts_on_car_switch() {
+        let mut sim = Sim::new(MapKind::RightAngleTurn.build(VehicleClass::Car.spec()));
+        let mut tracker = AttemptTracker::new();
+        for _ in 0..30 {
+            sim.speed = 2.;
+            let events = sim.step(0.1);
+            assert!(tracker.update(&sim, &events).is_none());
+        }
+        sim.speed = -2.;
+        let events = sim.step(0.1);
+        tracker.update(&sim, &events);
+        assert!(tracker.distance > 5.);
+        assert_eq!(tracker.reversals, 1);
+        // 加的新车在起点, 两辆车后轴之间的距离不能算进去
+        assert!(sim.add_car());
+        let time = sim.time;
+        let events = sim.step(0.1);
+        assert!(tracker.update(&sim, &events).is_none());
+        assert_eq!(tracker.distance, 0.);
+        assert_eq!(tracker.reversals, 0);
+        assert_eq!(tracker.gear, Gear::Neutral);
+        // 新车考完时记下新车这次练习的结果和时长
+        sim.speed = 2.;
+        let events = sim.step(0.1);
+        tracker.update(&sim, &events);
+        sim.exam.time_out();
+        let attempt = tracker.update(&sim, &[]).unwrap();
+        assert!(!attempt.passed);
+        assert!((attempt.duration - (sim.time - time)).abs() < 1e-4);
+        assert!((attempt.distance - 0.2).abs() < 1e-4);
+        // 换回第一辆车重新开始统计, 再换回考完的车不再记一次
+        sim.switch_car(None);
+        for _ in 0..2 {
+            sim.speed = 2.;
+            let events = sim.step(0.1);
+            assert!(tracker.update(&sim, &events).is_none());
+        }
+        assert!(!tracker.finished);
+        assert!(tracker.distance > 0. && tracker.distance < 0.3);
+        sim.switch_car(None);
+        sim.step(0.1);
+        assert!(tracker.update(&sim, &[]).is_none());
+        assert!(tracker.finished);
+    }
+}
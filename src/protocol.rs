@@ -0,0 +1,135 @@
+// 远程控制协议, 每行一个JSON对象, 服务端见图形界面的remote.rs
+//
+// 客户端 -> 仿真:
+//   {"cmd": "steer", "angle": 2.5}             方向盘目标角度(圈), [-lock_turns, lock_turns], lock_turns是当前车型打满的圈数, 向左为正, 按转向速度转过去
+//   {"cmd": "throttle", "value": 0.5}          油门, [0, 1], 对应速度 0 ~ 最高车速, 和键盘一样按设置面板的车速, 倒车不超过SPEED
+//   {"cmd": "gear", "value": "D"}              档位, "D"/"N"/"R"
+//   {"cmd": "reset"}
+//   {"cmd": "load_map", "map": "back_parking"} back_parking/parallel_parking/right_angle_turn/crossing
+//
+// 仿真 -> 客户端:
+//   {"type": "state", ...}   车的位姿、四个车轮的位置、倒车雷达读数、后轮转向比例、挂车夹角(没有挂车时为null)、横摆角速度和质心侧偏角(运动学模型时为null)和交通参与者的位置
+//   {"type": "event", ...}   考试事件
+//   {"type": "error", ...}   命令无法解析
+use crate::{MapKind, Point, exam::ExamEvent, json::Value, sim::{Gear, Sim}};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RemoteCommand {
+    Steer(f32),
+    Throttle(f32),
+    Gear(Gear),
+    Reset,
+    LoadMap(MapKind),
+}
+
+impl RemoteCommand {
+    pub fn parse(line: &str) -> Result<RemoteCommand, String> {
+        let value = Value::parse(line)?;
+        let field = |key: &str| value.get(key).ok_or(format!("missing field '{}'", key));
+        match field("cmd")?.as_str() {
+            Some("steer") => field("angle")?.as_f32().map(RemoteCommand::Steer)
+                .ok_or_else(|| "'angle' must be a number".to_string()),
+            Some("throttle") => field("value")?.as_f32().map(|t| RemoteCommand::Throttle(t.clamp(0., 1.)))
+                .ok_or_else(|| "'value' must be a number".to_string()),
+            Some("gear") => field("value")?.as_str().and_then(Gear::from_name).map(RemoteCommand::Gear)
+                .ok_or_else(|| "'value' must be one of \"D\", \"N\", \"R\"".to_string()),
+            Some("reset") => Ok(RemoteCommand::Reset),
+            Some("load_map") => field("map")?.as_str().and_then(MapKind::from_name).map(RemoteCommand::LoadMap)
+                .ok_or_else(|| "unknown map".to_string()),
+            _ => Err("unknown cmd".to_string()),
+        }
+    }
+}
+
+pub fn state_message(sim: &Sim) -> Value {
+    let car = &sim.car;
+    let point = |p: Point| Value::Array(vec![p.x.into(), p.y.into()]);
+    let back_origin = car.back_origin();
+    Value::Object(vec![
+        ("type".to_string(), "state".into()),
+        ("map".to_string(), sim.map.kind().name().into()),
+        ("length".to_string(), car.spec.length.into()),
+        ("width".to_string(), car.spec.width.into()),
+        ("time".to_string(), sim.time.into()),
+        ("x".to_string(), back_origin.x.into()),
+        ("y".to_string(), back_origin.y.into()),
+        ("heading".to_string(), car.heading().into()),
+        ("speed".to_string(), sim.speed.into()),
+        ("gear".to_string(), Gear::of(sim.speed).name().into()),
+        ("steer_angle".to_string(), car.steer_angle.into()),
+        ("rear_steer_ratio".to_string(), car.rear_ratio.into()),
+        ("yaw_rate".to_string(), sim.dynamics.as_ref().map_or(Value::Null, |model| model.yaw_rate.into())),
+        ("slip_angle".to_string(), sim.dynamics.as_ref().map_or(Value::Null, |model| model.slip_angle(sim.speed).into())),
+        ("wheels".to_string(), Value::Array(car.wheels().iter().map(|w| point(w.origin)).collect())),
+        ("ultrasonic".to_string(), Value::Array(sim.ultrasonic.distances().into_iter().map(Value::from).collect())),
+        ("trailer_angle".to_string(), car.trailer.map_or(Value::Null, |trailer| trailer.articulation(car).into())),
+        ("agents".to_string(), Value::Array(sim.agents.iter().map(|a| point(a.position)).collect())),
+        ("score".to_string(), sim.exam.score().into()),
+        ("status".to_string(), sim.exam.status().name().into()),
+    ])
+}
+
+pub fn event_message(event: ExamEvent, sim: &Sim) -> Value {
+    Value::Object(vec![
+        ("type".to_string(), "event".into()),
+        ("event".to_string(), event.name().into()),
+        ("deduction".to_string(), event.deduction().into()),
+        ("score".to_string(), sim.exam.score().into()),
+        ("status".to_string(), sim.exam.status().name().into()),
+    ])
+}
+
+pub fn error_message(message: &str) -> Value {
+    Value::Object(vec![
+        ("type".to_string(), "error".into()),
+        ("message".to_string(), message.into()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RemoteCommand, error_message, event_message, state_message};
+    use crate::{MapKind, exam::ExamEvent, json::Value, sim::{Gear, Sim}, vehicle::VehicleClass};
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "steer", "angle": -1.5}"#), Ok(RemoteCommand::Steer(-1.5)));
+        // 油门限制在[0, 1]
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "throttle", "value": 2}"#), Ok(RemoteCommand::Throttle(1.)));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "throttle", "value": -1}"#), Ok(RemoteCommand::Throttle(0.)));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "gear", "value": "R"}"#), Ok(RemoteCommand::Gear(Gear::Reverse)));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "reset"}"#), Ok(RemoteCommand::Reset));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "load_map", "map": "right_angle_turn"}"#),
+            Ok(RemoteCommand::LoadMap(MapKind::RightAngleTurn)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "fly"}"#), Err("unknown cmd".to_string()));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "steer"}"#), Err("missing field 'angle'".to_string()));
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "steer", "angle": "left"}"#),
+            Err("'angle' must be a number".to_string()));
+        assert!(RemoteCommand::parse(r#"{"cmd": "gear", "value": "P"}"#).is_err());
+        assert_eq!(RemoteCommand::parse(r#"{"cmd": "load_map", "map": "moon"}"#), Err("unknown map".to_string()));
+        assert!(RemoteCommand::parse("{\"cmd\": ").is_err());
+    }
+
+    #[test]
+    fn messages() {
+        let sim = Sim::new(MapKind::BackParking.build(VehicleClass::Car.spec()));
+        let state = Value::parse(&state_message(&sim).to_string()).unwrap();
+        assert_eq!(state.get("type").and_then(Value::as_str), Some("state"));
+        assert_eq!(state.get("map").and_then(Value::as_str), Some("back_parking"));
+        assert_eq!(state.get("x").and_then(Value::as_f32), Some(sim.car.back_origin().x));
+        assert_eq!(state.get("gear").and_then(Value::as_str), Some("N"));
+        assert_eq!(state.get("wheels").and_then(Value::as_array).map(|wheels| wheels.len()), Some(4));
+        // 没有挂车和动力学模型时为null
+        assert_eq!(state.get("trailer_angle"), Some(&Value::Null));
+        assert_eq!(state.get("yaw_rate"), Some(&Value::Null));
+
+        let event = event_message(ExamEvent::LineTouched, &sim);
+        assert_eq!(event.get("event").and_then(Value::as_str), Some("line_touched"));
+        assert_eq!(event.get("deduction").and_then(Value::as_f32), Some(100.));
+        assert_eq!(error_message("unknown cmd").to_string(), r#"{"type":"error","message":"unknown cmd"}"#);
+    }
+}
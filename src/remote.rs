@@ -0,0 +1,259 @@
+// 本地TCP远程控制, 消息格式见car_simulation::protocol
+use std::{io::{ErrorKind, Read, Write}, net::{TcpListener, TcpStream}};
+
+use car_simulation::{exam::ExamEvent, json::Value, protocol::{self, RemoteCommand}, sim::{Gear, Sim}};
+
+// 每秒最多发送的状态数
+const STATE_RATE: f32 = 30.;
+// 客户端积压这么多字节还没收走就断开
+const MAX_OUTGOING: usize = 1 << 20;
+// 一条命令最长的字节数, 一直不发换行的客户端断开
+const MAX_LINE: usize = 1 << 16;
+
+struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    // 还没写进socket的数据, 非阻塞写一次可能只写进去一部分, 剩下的下一帧接着写
+    outgoing: Vec<u8>,
+    closed: bool,
+}
+
+impl Client {
+    fn send(&mut self, message: &Value) {
+        self.outgoing.extend_from_slice(format!("{}\n", message).as_bytes());
+        if self.outgoing.len() > MAX_OUTGOING {
+            self.closed = true;
+        }
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+}
+
+pub struct RemoteServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    throttle: f32,
+    gear: Gear,
+    // 收到过油门或档位命令后由远程控制车速
+    driving: bool,
+    // 收到过转向命令后由远程控制握着方向盘
+    steering: bool,
+    // 上次发送状态时的仿真时间
+    last_state: Option<f32>,
+}
+
+impl RemoteServer {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(RemoteServer {
+            listener,
+            clients: vec![],
+            throttle: 0.,
+            gear: Gear::Neutral,
+            driving: false,
+            steering: false,
+            last_state: None,
+        })
+    }
+
+    // 接受新连接, 返回所有客户端发来的完整命令, 不会阻塞
+    pub fn poll(&mut self) -> Vec<RemoteCommand> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                stream.set_nodelay(true).ok();
+                self.clients.push(Client { stream, buffer: vec![], outgoing: vec![], closed: false });
+            }
+        }
+        let mut commands = vec![];
+        for client in self.clients.iter_mut() {
+            client.flush();
+            let mut buf = [0u8; 4096];
+            loop {
+                match client.stream.read(&mut buf) {
+                    Ok(0) => {
+                        client.closed = true;
+                        break;
+                    },
+                    Ok(n) => client.buffer.extend_from_slice(&buf[..n]),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        client.closed = true;
+                        break;
+                    },
+                }
+            }
+            while let Some(i) = client.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = client.buffer.drain(..=i).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match RemoteCommand::parse(line.trim()) {
+                    Ok(command) => commands.push(command),
+                    Err(message) => client.send(&protocol::error_message(&message)),
+                }
+            }
+            if client.buffer.len() > MAX_LINE {
+                client.send(&protocol::error_message("line too long"));
+                client.closed = true;
+            }
+        }
+        self.clients.retain(|c| !c.closed);
+        for command in commands.iter() {
+            match command {
+                RemoteCommand::Throttle(throttle) => {
+                    self.throttle = *throttle;
+                    self.driving = true;
+                },
+                RemoteCommand::Gear(gear) => {
+                    self.gear = *gear;
+                    self.driving = true;
+                },
+                RemoteCommand::Reset | RemoteCommand::LoadMap(_) => {
+                    self.throttle = 0.;
+                },
+                RemoteCommand::Steer(_) => self.steering = true,
+            }
+        }
+        if self.clients.is_empty() {
+            self.driving = false;
+            self.steering = false;
+        }
+        commands
+    }
+
+    // 远程控制的档位和油门, 没有远程控制时为None
+    pub fn drive(&self) -> Option<(Gear, f32)> {
+        if self.driving {
+            Some((self.gear, self.throttle))
+        } else {
+            None
+        }
+    }
+
+    // 有客户端在转方向盘, 这时方向盘不自动回正
+    pub fn steering(&self) -> bool {
+        self.steering
+    }
+
+    pub fn send(&mut self, message: &Value) {
+        for client in self.clients.iter_mut() {
+            client.send(message);
+        }
+        self.clients.retain(|c| !c.closed);
+    }
+
+    pub fn send_state(&mut self, sim: &Sim) {
+        if self.clients.is_empty() {
+            return;
+        }
+        // 重新开始后仿真时间归零, 马上发一次
+        if let Some(last) = self.last_state {
+            if sim.time >= last && sim.time - last < 1./STATE_RATE {
+                return;
+            }
+        }
+        self.last_state = Some(sim.time);
+        let state = protocol::state_message(sim);
+        // 状态只要最新的, 上一条还没收走就不再往后排; 事件和错误不会跳过
+        for client in self.clients.iter_mut().filter(|client| client.outgoing.is_empty()) {
+            client.send(&state);
+        }
+        self.clients.retain(|c| !c.closed);
+    }
+
+    pub fn send_event(&mut self, event: ExamEvent, sim: &Sim) {
+        self.send(&protocol::event_message(event, sim));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Write}, net::TcpStream, time::{Duration, SystemTime}};
+
+    use car_simulation::{MapKind, json::Value, protocol::RemoteCommand, sim::Sim, vehicle::VehicleClass};
+
+    use super::RemoteServer;
+
+    // 非阻塞的服务端要多轮询几次才能收到连接和数据
+    fn poll_until(server: &mut RemoteServer) -> Vec<RemoteCommand> {
+        let start = SystemTime::now();
+        loop {
+            let commands = server.poll();
+            if !commands.is_empty() || start.elapsed().unwrap() > Duration::from_secs(5) {
+                return commands;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn loopback() {
+        let mut server = RemoteServer::bind(0).unwrap();
+        let port = server.listener.local_addr().unwrap().port();
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        // 命令分两次写, 服务端要按换行拼成完整的一行
+        stream.write_all(br#"{"cmd": "steer", "#).unwrap();
+        stream.flush().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        server.poll();
+        stream.write_all(b"\"angle\": 1.5}\n").unwrap();
+        let commands = poll_until(&mut server);
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(commands[0], RemoteCommand::Steer(angle) if angle == 1.5));
+
+        let sim = Sim::new(MapKind::ParallelParking.build(VehicleClass::Car.spec()));
+        server.send_state(&sim);
+        // 同一时刻的状态只发一次
+        server.send_state(&sim);
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let state = Value::parse(line.trim()).unwrap();
+        assert_eq!(state.get("type").and_then(Value::as_str), Some("state"));
+        assert_eq!(state.get("map").and_then(Value::as_str), Some("parallel_parking"));
+        assert_eq!(state.get("x").and_then(Value::as_f32), Some(sim.car.back_origin().x));
+        reader.get_ref().set_nonblocking(true).unwrap();
+        line.clear();
+        assert!(reader.read_line(&mut line).is_err());
+    }
+
+    #[test]
+    fn steering_only_while_a_client_steers() {
+        let mut server = RemoteServer::bind(0).unwrap();
+        assert!(!server.steering());
+        let port = server.listener.local_addr().unwrap().port();
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.write_all(b"{\"cmd\": \"throttle\", \"value\": 0.5}\n").unwrap();
+        poll_until(&mut server);
+        assert!(server.drive().is_some());
+        assert!(!server.steering());
+        stream.write_all(b"{\"cmd\": \"steer\", \"angle\": 1}\n").unwrap();
+        poll_until(&mut server);
+        assert!(server.steering());
+        // 客户端断开后交还方向盘
+        drop(stream);
+        let start = SystemTime::now();
+        while server.steering() && start.elapsed().unwrap() < Duration::from_secs(5) {
+            server.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!server.steering());
+        assert!(server.drive().is_none());
+    }
+}
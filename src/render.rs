@@ -0,0 +1,139 @@
+use lazy_static::lazy_static;
+use raqote::{DrawTarget, SolidSource, Source, DrawOptions, PathBuilder, ExtendMode, FilterMode, Transform, BlendMode, AntialiasMode, StrokeStyle};
+
+use car_simulation::{Canvas, Color, Point, Rect, Sprite, LOGO_WIDTH, LOGO_HEIGHT, SCALE, WINDOW_HEIGHT};
+
+use crate::real2pixel;
+
+pub fn solid(color: Color) -> SolidSource {
+    SolidSource::from_unpremultiplied_argb(color.a, color.r, color.g, color.b)
+}
+
+// svg按仿真里的尺寸(m)渲染成的位图
+struct Bitmap {
+    data: Vec<u32>,
+    width: i32,
+    height: i32,
+}
+
+impl Bitmap {
+    fn from_svg(path: &std::path::Path, width: f32, height: f32) -> Self {
+        let svg = usvg::Tree::from_data(&std::fs::read(path).unwrap(), &usvg::Options::default().to_ref()).unwrap();
+        let (width, height) = ((width*SCALE) as u32, (height*SCALE) as u32);
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).unwrap();
+        resvg::render(&svg, usvg::FitTo::Size(width, height), pixmap.as_mut()).unwrap();
+        let mut data = vec![];
+        for chunk in pixmap.data().chunks(4) {
+            if let &[r, g, b, a] = chunk {
+                data.push(u32::from_be_bytes([a, r, g, b]));
+            }
+        }
+        Bitmap { data, width: width as i32, height: height as i32 }
+    }
+}
+
+lazy_static! {
+    // 每辆车都用同一个logo, 只渲染一次
+    static ref LOGO: Bitmap = Bitmap::from_svg(std::path::Path::new("res/tesla.svg"), LOGO_WIDTH, LOGO_HEIGHT);
+}
+
+fn path(points: &[Point], close: bool) -> raqote::Path {
+    let mut pb = PathBuilder::new();
+    for (i, p) in points.iter().enumerate() {
+        let (x, y) = real2pixel(*p).into();
+        if i == 0 {
+            pb.move_to(x, y);
+        } else {
+            pb.line_to(x, y);
+        }
+    }
+    if close {
+        pb.close();
+    }
+    pb.finish()
+}
+
+fn circle(center: Point, radius: f32) -> raqote::Path {
+    let (x, y) = real2pixel(center).into();
+    let mut pb = PathBuilder::new();
+    pb.arc(x, y, radius*SCALE, 0., 2.*std::f32::consts::PI);
+    pb.close();
+    pb.finish()
+}
+
+const DRAW_OPTIONS: DrawOptions = DrawOptions {
+    blend_mode: BlendMode::SrcOver,
+    alpha: 1.,
+    antialias: AntialiasMode::Gray,
+};
+
+// 把仿真里的View画到raqote的DrawTarget上, 世界坐标按real2pixel换成像素
+pub struct Painter<'a> {
+    dt: &'a mut DrawTarget,
+}
+
+impl<'a> Painter<'a> {
+    pub fn new(dt: &'a mut DrawTarget) -> Self {
+        Painter { dt }
+    }
+}
+
+impl Canvas for Painter<'_> {
+    fn clear(&mut self, color: Color) {
+        self.dt.clear(solid(color));
+    }
+
+    fn fill_polygon(&mut self, points: &[Point], color: Color) {
+        self.dt.fill(&path(points, true), &Source::Solid(solid(color)), &DRAW_OPTIONS);
+    }
+
+    fn fill_circle(&mut self, center: Point, radius: f32, color: Color) {
+        self.dt.fill(&circle(center, radius), &Source::Solid(solid(color)), &DRAW_OPTIONS);
+    }
+
+    fn stroke_polyline(&mut self, points: &[Point], width: f32, color: Color) {
+        self.dt.stroke(&path(points, false), &Source::Solid(solid(color)),
+            &StrokeStyle { width, ..StrokeStyle::default() }, &DRAW_OPTIONS);
+    }
+
+    fn stroke_circle(&mut self, center: Point, radius: f32, width: f32, color: Color) {
+        self.dt.stroke(&circle(center, radius), &Source::Solid(solid(color)),
+            &StrokeStyle { width, ..StrokeStyle::default() }, &DRAW_OPTIONS);
+    }
+
+    fn sprite(&mut self, sprite: Sprite, outline: &Rect) {
+        let bitmap: &Bitmap = match sprite {
+            Sprite::Logo => &LOGO,
+        };
+        let image = raqote::Image {
+            width: bitmap.width,
+            height: bitmap.height,
+            data: bitmap.data.as_slice(),
+        };
+        let rot_inv = outline.rotation_matrix.inverse().unwrap();
+        let origin = outline.origin;
+        let corners = [outline.lt(), outline.rt(), outline.rb(), outline.lb()];
+        self.dt.fill(
+            &path(&corners, true),
+            &Source::Image(
+                image,
+                ExtendMode::Pad,
+                FilterMode::Bilinear,
+                Transform::row_major(1./SCALE, 0., 0., -1./SCALE, 0., WINDOW_HEIGHT)
+                    .post_transform(&Transform::create_translation(-origin.x, -origin.y))
+                    .post_transform(&Transform::row_major(
+                        rot_inv.inner[0][0],
+                        rot_inv.inner[1][0],
+                        rot_inv.inner[0][1],
+                        rot_inv.inner[1][1],
+                        outline.width/2.,
+                        outline.height/2.,
+                    )).post_transform(&Transform::row_major(
+                        (bitmap.width as f32)/outline.width, 0., 0., -(bitmap.height as f32)/outline.height,
+                        0., bitmap.height as f32,
+                    ))
+            ),
+            &DRAW_OPTIONS,
+        );
+    }
+}
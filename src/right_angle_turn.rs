@@ -1,24 +1,26 @@
-use crate::{Rect, point2, WINDOW_HEIGHT, WINDOW_WIDTH, MENU_WIDTH, CAR_HEIGHT, Map, View, Car};
-
-use raqote::{SolidSource, DrawTarget};
+use crate::{Color, Canvas, Rect, point2, geometry::Segment, obstacle::Obstacle, WINDOW_HEIGHT, WINDOW_WIDTH, MENU_WIDTH, CAR_WIDTH, Map, MapKind, View, Car, vehicle::VehicleSpec};
 
 const MAP_WIDTH: f32 = WINDOW_WIDTH - MENU_WIDTH;
 const MAP_HEIGHT: f32 = WINDOW_HEIGHT;
+// 小型汽车的路宽, 大车按车宽等比例放大
 const ROAD_WIDTH: f32 = 4.0;
 
 pub struct RightAngleTurn {
+    spec: VehicleSpec,
     road_horizontal: Rect,
     road_vertical: Rect,
 }
 
 impl RightAngleTurn {
-    pub fn new() -> Self {
-        let color = SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff);
-        let road_vertical = Rect::new(point2(MAP_WIDTH-ROAD_WIDTH/2.-0.3, MAP_HEIGHT/2.), 
-            ROAD_WIDTH, MAP_HEIGHT-0.6, Some(color));
-        let road_horizontal = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT-ROAD_WIDTH/2.-0.3), 
-            MAP_WIDTH-0.6, ROAD_WIDTH, Some(color));
+    pub fn new(spec: VehicleSpec) -> Self {
+        let color = Color::rgb(0xff, 0xff, 0xff);
+        let road_width = ROAD_WIDTH*spec.width/CAR_WIDTH;
+        let road_vertical = Rect::new(point2(MAP_WIDTH-road_width/2.-0.3, MAP_HEIGHT/2.), 
+            road_width, MAP_HEIGHT-0.6, Some(color));
+        let road_horizontal = Rect::new(point2(MAP_WIDTH/2., MAP_HEIGHT-road_width/2.-0.3), 
+            MAP_WIDTH-0.6, road_width, Some(color));
         RightAngleTurn {
+            spec,
             road_horizontal,
             road_vertical,
         }
@@ -26,8 +28,38 @@ impl RightAngleTurn {
 }
 
 impl Map for RightAngleTurn {
+    fn kind(&self) -> MapKind {
+        MapKind::RightAngleTurn
+    }
+
     fn car(&self) -> Car {
-        Car::new(point2(self.road_vertical.origin.x, CAR_HEIGHT), 0.)
+        Car::new(self.spec, point2(self.road_vertical.origin.x, self.spec.length), 0.)
+    }
+
+    fn lines(&self) -> Vec<Segment> {
+        let [_, right, bottom, _] = self.road_vertical.edges();
+        let [top, _, _, left] = self.road_horizontal.edges();
+        vec![
+            top,
+            right,
+            bottom,
+            left,
+            // 内侧的两条边线在拐角处相交
+            Segment::new(self.road_vertical.lb(), point2(self.road_vertical.lb().x, self.road_horizontal.lb().y)),
+            Segment::new(self.road_horizontal.lb(), point2(self.road_vertical.lb().x, self.road_horizontal.lb().y)),
+        ]
+    }
+
+    fn goal(&self) -> Rect {
+        let origin = self.road_horizontal.lb();
+        Rect::new(point2(origin.x+self.spec.length, self.road_horizontal.origin.y),
+            self.spec.length*2., self.road_horizontal.height, None)
+    }
+
+    // 拐角外侧的立柱
+    fn obstacles(&self) -> Vec<Obstacle> {
+        let corner = self.road_vertical.rt();
+        vec![Obstacle::Pole(point2(corner.x+0.15, corner.y+0.15))]
     }
 }
 
@@ -36,10 +68,10 @@ impl View for RightAngleTurn {
         (MENU_WIDTH, 0.).into()
     }
 
-    fn draw(&self, dt: &mut DrawTarget, translation: crate::linear_algebra::Vector2D) {
+    fn draw(&self, canvas: &mut dyn Canvas, translation: crate::linear_algebra::Vector2D) {
         let translation = self.relative_translation() + translation;
-        dt.clear(SolidSource::from_unpremultiplied_argb(0xff, 0x00, 0x00, 0x00));
-        self.road_horizontal.draw(dt, translation);
-        self.road_vertical.draw(dt, translation);
+        canvas.clear(Color::rgb(0x00, 0x00, 0x00));
+        self.road_horizontal.draw(canvas, translation);
+        self.road_vertical.draw(canvas, translation);
     }
 }
\ No newline at end of file
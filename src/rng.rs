@@ -0,0 +1,34 @@
+// splitmix64, 同一个seed得到同样的序列
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high-low)*self.next_f32()
+    }
+
+    // Box-Muller
+    pub fn gaussian(&mut self, mean: f32, std_dev: f32) -> f32 {
+        let u1 = 1. - self.next_f32();
+        let u2 = self.next_f32();
+        mean + std_dev*f32::sqrt(-2.*f32::ln(u1))*f32::cos(2.*std::f32::consts::PI*u2)
+    }
+}
@@ -0,0 +1,327 @@
+use font_kit::font::Font;
+use raqote::DrawTarget;
+
+use car_simulation::{MapKind, exam::{ExamEvent, ExamStatus, FULL_SCORE, PASS_SCORE}, json::Value, sim::Sim};
+
+use crate::{i18n::{self, Message}, ui};
+
+// 开考前播报和每项结束后显示结果的时间(s), 这段时间车不能动
+const ANNOUNCE_TIME: f32 = 3.;
+const RESULT_TIME: f32 = 3.;
+// 考试中画面上显示最近几条扣分
+const LOG_LINES: usize = 5;
+
+// 每项的限时(s)
+fn time_limit(kind: MapKind) -> f32 {
+    match kind {
+        MapKind::BackParking => 210.,
+        MapKind::ParallelParking => 90.,
+        MapKind::RightAngleTurn => 60.,
+        MapKind::Crossing => 60.,
+    }
+}
+
+fn status_text(status: ExamStatus) -> &'static str {
+    i18n::tr(if status == ExamStatus::Passed {Message::Passed} else {Message::Failed})
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Phase {
+    // 播报项目和限时
+    Announcing,
+    Driving,
+    // 显示这一项的结果
+    ItemResult,
+    // 全部考完、某一项不合格或总分低于合格线, 显示成绩单
+    Finished,
+}
+
+struct Deduction {
+    // 当前项目开始后的时间
+    time: f32,
+    event: ExamEvent,
+}
+
+struct ItemResult {
+    kind: MapKind,
+    status: ExamStatus,
+    time: f32,
+}
+
+// 考试模式: 和真实考试一样按顺序考各个项目, 每项限时, 一项不合格或总分低于合格线考试就结束
+// 总分从100分开始, 所有项目的扣分累计
+pub struct ExamSession {
+    items: Vec<MapKind>,
+    current: usize,
+    phase: Phase,
+    // 当前阶段已经过的时间
+    elapsed: f32,
+    deductions: Vec<Deduction>,
+    results: Vec<ItemResult>,
+}
+
+impl ExamSession {
+    pub fn new(items: Vec<MapKind>) -> Self {
+        assert!(!items.is_empty(), "exam needs at least one item");
+        let session = ExamSession {
+            items,
+            current: 0,
+            phase: Phase::Announcing,
+            elapsed: 0.,
+            deductions: vec![],
+            results: vec![],
+        };
+        println!("{}", session.announcement());
+        session
+    }
+
+    // 配置文件的"exam"部分: {"items": ["back_parking", "parallel_parking"]}, 默认考所有项目
+    pub fn items_from_json(value: Option<&Value>) -> Result<Vec<MapKind>, String> {
+        let items = match value.and_then(|value| value.get("items")) {
+            Some(items) => items.as_array().ok_or("exam items must be an array")?,
+            None => return Ok(MapKind::ALL.to_vec()),
+        };
+        let items: Vec<MapKind> = items.iter()
+            .map(|item| item.as_str().and_then(MapKind::from_name).ok_or(format!("unknown exam item: {:?}", item)))
+            .collect::<Result<_, _>>()?;
+        if items.is_empty() {
+            return Err("exam items must not be empty".to_string());
+        }
+        Ok(items)
+    }
+
+    // 当前考的项目, 开考和换项目时按这个加载地图
+    pub fn item(&self) -> MapKind {
+        self.items[self.current]
+    }
+
+    // 只有考试进行中车才能动
+    pub fn driving(&self) -> bool {
+        self.phase == Phase::Driving
+    }
+
+    pub fn score(&self) -> i32 {
+        (FULL_SCORE - self.deductions.iter().map(|d| d.event.deduction()).sum::<i32>()).max(0)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.results.len() == self.items.len()
+            && self.results.iter().all(|result| result.status == ExamStatus::Passed)
+            && self.score() >= PASS_SCORE
+    }
+
+    fn announcement(&self) -> String {
+        i18n::format(Message::ExamAnnounce, &[&i18n::tr(self.item().into()), &time_limit(self.item())])
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.elapsed = 0.;
+    }
+
+    // 每帧在Sim::step之后调用, 到了限时还没完成时判超时, 返回的事件和其他考试事件一样处理
+    pub fn check_time(&self, sim: &mut Sim) -> Option<ExamEvent> {
+        if self.phase == Phase::Driving && self.elapsed > time_limit(self.item()) {
+            sim.exam.time_out()
+        } else {
+            None
+        }
+    }
+
+    // events是这一帧的考试事件, 返回需要加载的下一个项目
+    pub fn update(&mut self, sim: &Sim, events: &[ExamEvent], dt: f32) -> Option<MapKind> {
+        self.elapsed += dt;
+        match self.phase {
+            Phase::Announcing => if self.elapsed >= ANNOUNCE_TIME {
+                self.enter(Phase::Driving);
+            },
+            Phase::Driving => {
+                for &event in events.iter().filter(|event| event.deduction() > 0) {
+                    self.deductions.push(Deduction { time: self.elapsed, event });
+                }
+                if sim.exam.status() != ExamStatus::Running {
+                    let result = ItemResult { kind: self.item(), status: sim.exam.status(), time: self.elapsed };
+                    println!("{}", self.result_text(&result));
+                    self.results.push(result);
+                    self.enter(Phase::ItemResult);
+                }
+            },
+            Phase::ItemResult => if self.elapsed >= RESULT_TIME {
+                // 某一项不合格, 或者各项累计扣分已经到了合格线以下, 后面的项目不考了
+                let failed = self.results.iter().any(|result| result.status != ExamStatus::Passed)
+                    || self.score() < PASS_SCORE;
+                if failed || self.current + 1 == self.items.len() {
+                    self.enter(Phase::Finished);
+                    println!("{}", self.final_text());
+                } else {
+                    self.current += 1;
+                    self.enter(Phase::Announcing);
+                    println!("{}", self.announcement());
+                    return Some(self.item());
+                }
+            },
+            Phase::Finished => {},
+        }
+        None
+    }
+
+    fn result_text(&self, result: &ItemResult) -> String {
+        i18n::format(Message::ItemResult,
+            &[&i18n::tr(result.kind.into()), &status_text(result.status), &format!("{:.0}", result.time)])
+    }
+
+    fn final_text(&self) -> String {
+        let status = if self.passed() {ExamStatus::Passed} else {ExamStatus::Failed};
+        i18n::format(Message::ExamResult, &[&status_text(status), &self.score()])
+    }
+
+    fn deduction_text(&self, deduction: &Deduction) -> String {
+        i18n::format(Message::DeductionEntry, &[&format!("{:.0}", deduction.time),
+            &i18n::tr(deduction.event.into()), &deduction.event.deduction()])
+    }
+
+    // 画在地图视口左上角. 考试中: 播报、倒计时和最近的扣分; 考完: 每项结果、全部扣分和总成绩
+    pub fn draw(&self, dt: &mut DrawTarget, font: &Font, (left, top, width, _): (f32, f32, f32, f32)) {
+        let mut lines = vec![];
+        match self.phase {
+            Phase::Announcing => {
+                lines.push(self.announcement());
+                lines.push(format!("{:.0}", (ANNOUNCE_TIME - self.elapsed).ceil()));
+            },
+            Phase::Driving => {
+                let remaining = (time_limit(self.item()) - self.elapsed).max(0.);
+                lines.push(i18n::format(Message::TimeLeft, &[&format!("{:.0}", remaining.ceil())]));
+                let start = self.deductions.len().saturating_sub(LOG_LINES);
+                lines.extend(self.deductions[start..].iter().map(|deduction| self.deduction_text(deduction)));
+            },
+            Phase::ItemResult => {
+                lines.extend(self.results.last().map(|result| self.result_text(result)));
+            },
+            Phase::Finished => {
+                lines.extend(self.results.iter().map(|result| self.result_text(result)));
+                lines.extend(self.deductions.iter().map(|deduction| self.deduction_text(deduction)));
+                lines.push(self.final_text());
+            },
+        }
+        ui::draw_text_box(dt, font, (left + ui::BOX_MARGIN, top + ui::BOX_MARGIN),
+            (width - 2.*ui::BOX_MARGIN).min(420.), &lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use car_simulation::{Car, distance_of, vehicle::VehicleClass};
+
+    const DT: f32 = 0.1;
+
+    // 和主循环一样每帧先走仿真再检查限时, 返回要加载的下一个项目
+    // 按DT累加的时间有舍入误差, 等播报和结果显示完时多走一步
+    fn run(session: &mut ExamSession, sim: &mut Sim, speed: f32, time: f32) -> Option<MapKind> {
+        let mut next = None;
+        for _ in 0..(time/DT).round() as usize {
+            sim.speed = if session.driving() {speed} else {0.};
+            let mut events = sim.step(DT);
+            events.extend(session.check_time(sim));
+            next = next.or(session.update(sim, &events, DT));
+        }
+        next
+    }
+
+    // 中途停一次车, 扣5分
+    fn stop_and_go(session: &mut ExamSession, sim: &mut Sim) {
+        run(session, sim, 0., 2.5);
+        run(session, sim, 2., 0.2);
+    }
+
+    // 把车横着摆到终点区域里停下
+    fn park(session: &mut ExamSession, sim: &mut Sim) {
+        sim.car = Car::new(sim.car.spec, sim.map.goal().origin, -std::f32::consts::PI/2.);
+        run(session, sim, 0., DT);
+    }
+
+    fn start(items: Vec<MapKind>) -> (ExamSession, Sim) {
+        let mut session = ExamSession::new(items);
+        let mut sim = Sim::new(session.item().build(VehicleClass::Car.spec()));
+        begin_item(&mut session, &mut sim);
+        (session, sim)
+    }
+
+    // 等播报完往前开一段, 播报时车不能动
+    fn begin_item(session: &mut ExamSession, sim: &mut Sim) {
+        let start = sim.car.body.origin;
+        run(session, sim, 2., ANNOUNCE_TIME + DT);
+        assert!(session.driving());
+        assert!(distance_of(sim.car.body.origin, start) < 1e-6);
+        run(session, sim, 2., 0.5);
+    }
+
+    // 考一项: 中途停stops次再停到位, 返回下一个项目
+    fn pass_item(session: &mut ExamSession, sim: &mut Sim, stops: usize) -> Option<MapKind> {
+        for _ in 0..stops {
+            stop_and_go(session, sim);
+        }
+        park(session, sim);
+        assert_eq!(session.phase, Phase::ItemResult);
+        run(session, sim, 0., RESULT_TIME + DT)
+    }
+
+    #[test]
+    fn time_limit() {
+        let (mut session, mut sim) = start(vec![MapKind::RightAngleTurn, MapKind::Crossing]);
+        let limit = super::time_limit(MapKind::RightAngleTurn);
+        run(&mut session, &mut sim, 0., limit - 1.);
+        assert!(session.driving());
+        assert_eq!(sim.exam.status(), ExamStatus::Running);
+        run(&mut session, &mut sim, 0., 1.5);
+        // 超时这一项不合格, 后面的项目不考了
+        assert_eq!(sim.exam.status(), ExamStatus::Failed);
+        assert_eq!(session.results.len(), 1);
+        assert_eq!(session.results[0].status, ExamStatus::Failed);
+        assert_eq!(session.deductions.iter().map(|d| d.event).collect::<Vec<_>>(), vec![ExamEvent::Timeout]);
+        assert_eq!(run(&mut session, &mut sim, 0., RESULT_TIME + DT), None);
+        assert_eq!(session.phase, Phase::Finished);
+        assert!(!session.passed());
+        assert_eq!(session.score(), 0);
+    }
+
+    #[test]
+    fn totals_across_items() {
+        let kind = MapKind::RightAngleTurn;
+        let (mut session, mut sim) = start(vec![kind, kind]);
+        assert_eq!(pass_item(&mut session, &mut sim, 1), Some(kind));
+        sim.load(kind.build(VehicleClass::Car.spec()));
+        begin_item(&mut session, &mut sim);
+        assert_eq!(pass_item(&mut session, &mut sim, 1), None);
+        assert_eq!(session.phase, Phase::Finished);
+        assert_eq!(session.score(), FULL_SCORE - 10);
+        assert!(session.passed());
+
+        // 每项单独都合格, 但累计扣分到了合格线以下
+        let (mut session, mut sim) = start(vec![kind, kind]);
+        assert_eq!(pass_item(&mut session, &mut sim, 3), Some(kind));
+        sim.load(kind.build(VehicleClass::Car.spec()));
+        begin_item(&mut session, &mut sim);
+        assert_eq!(pass_item(&mut session, &mut sim, 3), None);
+        assert!(session.results.iter().all(|result| result.status == ExamStatus::Passed));
+        assert_eq!(session.score(), FULL_SCORE - 30);
+        assert!(session.score() < PASS_SCORE);
+        assert!(!session.passed());
+    }
+
+    #[test]
+    fn deductions_end_exam_early() {
+        let kind = MapKind::RightAngleTurn;
+        let (mut session, mut sim) = start(vec![kind, kind, kind]);
+        assert_eq!(pass_item(&mut session, &mut sim, 3), Some(kind));
+        sim.load(kind.build(VehicleClass::Car.spec()));
+        begin_item(&mut session, &mut sim);
+        // 第二项合格, 但总分已经低于合格线, 不再考第三项
+        assert_eq!(pass_item(&mut session, &mut sim, 2), None);
+        assert_eq!(session.phase, Phase::Finished);
+        assert_eq!(session.results.len(), 2);
+        assert!(session.results.iter().all(|result| result.status == ExamStatus::Passed));
+        assert_eq!(session.score(), FULL_SCORE - 25);
+        assert!(!session.passed());
+    }
+}
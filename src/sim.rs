@@ -0,0 +1,383 @@
+use crate::{Car, Map, agent::Agent, vehicle::VehicleSpec, controller::Controller, dynamics::BicycleModel, exam::{Exam, ExamEvent}, geometry::Segment, lidar::Lidar, obstacle::Obstacle, steering::SteeringActuator, ultrasonic::Ultrasonic};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gear {
+    Drive,
+    Neutral,
+    Reverse,
+}
+
+impl Gear {
+    pub fn of(speed: f32) -> Gear {
+        if speed > 0. {
+            Gear::Drive
+        } else if speed < 0. {
+            Gear::Reverse
+        } else {
+            Gear::Neutral
+        }
+    }
+
+    pub fn sign(self) -> f32 {
+        match self {
+            Gear::Drive => 1.,
+            Gear::Neutral => 0.,
+            Gear::Reverse => -1.,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Gear::Drive => "D",
+            Gear::Neutral => "N",
+            Gear::Reverse => "R",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Gear> {
+        [Gear::Drive, Gear::Neutral, Gear::Reverse].iter().cloned().find(|gear| gear.name() == name)
+    }
+}
+
+// 场景里当前没有被操控的车, 有控制器时由控制器自动驾驶
+pub struct CarState {
+    pub car: Car,
+    pub speed: f32,
+    pub exam: Exam,
+    pub controller: Option<Box<dyn Controller>>,
+}
+
+impl CarState {
+    fn step(&mut self, dt: f32) {
+        if let Some(controller) = self.controller.as_mut() {
+            let command = controller.control(&self.car);
+            self.car.set_steer_angle(command.steer_angle);
+            self.speed = command.speed;
+            if controller.finished() {
+                self.controller = None;
+                self.speed = 0.;
+            }
+        }
+        self.car.adapt_rear_steer(self.speed);
+        if self.speed != 0. {
+            self.car.forward(self.speed*dt);
+        }
+    }
+}
+
+// 不依赖窗口和绘图的仿真状态, 图形界面和无界面环境共用
+// car/speed/exam是当前操控的车, 其他车在cars里, 切换时互相交换
+pub struct Sim {
+    pub map: Box<dyn Map>,
+    pub car: Car,
+    // 车速(m/s), <0 表示倒车
+    pub speed: f32,
+    pub exam: Exam,
+    pub time: f32,
+    // 加载地图、重置、加车或换车时加一, 换了一次练习的车或者重新开始, 练习统计要从头算
+    pub attempt: u32,
+    pub ultrasonic: Ultrasonic,
+    pub lidar: Option<Lidar>,
+    pub agents: Vec<Agent>,
+    pub cars: Vec<CarState>,
+    // None时当前操控的车用运动学模型, 其他车总是用运动学模型
+    pub dynamics: Option<BicycleModel>,
+    // None时转向输入立即生效, 否则按转向执行机构的转速慢慢转
+    pub steering: Option<SteeringActuator>,
+}
+
+impl Sim {
+    pub fn new(map: Box<dyn Map>) -> Self {
+        let car = map.car();
+        let agents = map.agents();
+        let mut sim = Sim {
+            map,
+            car,
+            speed: 0.,
+            exam: Exam::new(),
+            time: 0.,
+            attempt: 0,
+            ultrasonic: Ultrasonic::bumpers(),
+            lidar: None,
+            agents,
+            cars: vec![],
+            dynamics: None,
+            steering: None,
+        };
+        sim.update_sensors(None);
+        sim
+    }
+
+    pub fn load(&mut self, map: Box<dyn Map>) {
+        self.map = map;
+        self.cars.clear();
+        self.reset();
+    }
+
+    // 只重置当前操控的车, 其他车留在原地, 挂着挂车或开着后轮转向的话保留
+    pub fn reset(&mut self) {
+        let trailer = self.car.trailer.map(|trailer| trailer.config);
+        let rear_steer = self.car.rear_steer;
+        self.car = self.map.car();
+        self.car.set_trailer(trailer);
+        self.car.set_rear_steer(rear_steer);
+        self.agents = self.map.agents();
+        self.speed = 0.;
+        self.exam = Exam::new();
+        self.time = 0.;
+        self.attempt += 1;
+        if let Some(model) = self.dynamics.as_mut() {
+            model.reset();
+        }
+        self.sync_steering();
+        self.update_sensors(None);
+    }
+
+    // 设置面板改了车的尺寸: 当前车原地按新尺寸重建, 考场按新尺寸重新生成, 其他车不变
+    pub fn set_spec(&mut self, spec: VehicleSpec) {
+        self.map = self.map.kind().build(spec);
+        self.car.set_spec(spec);
+        self.sync_steering();
+        self.update_sensors(None);
+    }
+
+    // 在起点放一辆新车并切换过去, 原来的车停在原地
+    // 起点被车或挂车占着时不放, 否则两辆车一开始就算碰撞; 返回是否放了新车
+    pub fn add_car(&mut self) -> bool {
+        let start = self.map.car();
+        let occupied = std::iter::once(&self.car).chain(self.cars.iter().map(|state| &state.car))
+            .flat_map(|car| std::iter::once(car.body).chain(car.trailer.as_ref().map(|trailer| trailer.body())))
+            .any(|body| Obstacle::Vehicle(body).collides(&start.body));
+        if occupied {
+            return false;
+        }
+        let car = std::mem::replace(&mut self.car, start);
+        self.cars.push(CarState {
+            car,
+            speed: 0.,
+            exam: std::mem::take(&mut self.exam),
+            controller: None,
+        });
+        self.speed = 0.;
+        self.attempt += 1;
+        self.sync_steering();
+        self.update_sensors(None);
+        true
+    }
+
+    // 切换到下一辆车, controller是当前车的自动驾驶控制器, 切走后继续运行
+    // 返回切换到的车的控制器
+    pub fn switch_car(&mut self, controller: Option<Box<dyn Controller>>) -> Option<Box<dyn Controller>> {
+        if self.cars.is_empty() {
+            return controller;
+        }
+        let next = self.cars.remove(0);
+        // 没有控制器的车切走后没人开, 停在原地
+        let speed = std::mem::replace(&mut self.speed, next.speed);
+        self.cars.push(CarState {
+            car: std::mem::replace(&mut self.car, next.car),
+            speed: if controller.is_some() {speed} else {0.},
+            exam: std::mem::replace(&mut self.exam, next.exam),
+            controller,
+        });
+        if let Some(model) = self.dynamics.as_mut() {
+            model.reset();
+        }
+        self.attempt += 1;
+        self.sync_steering();
+        self.update_sensors(None);
+        next.controller
+    }
+
+    fn sync_steering(&mut self) {
+        if let Some(steering) = self.steering.as_mut() {
+            steering.sync(&self.car);
+        }
+    }
+
+    // 把方向盘转到angle圈, 有转向执行机构时只设置目标
+    pub fn steer_to(&mut self, angle: f32) {
+        match self.steering.as_mut() {
+            Some(steering) => steering.steer_to(angle),
+            None => self.car.set_steer_angle(angle),
+        }
+    }
+
+    // 方向盘当前要转到的圈数
+    pub fn steer_target(&self) -> f32 {
+        self.steering.as_ref().map_or(self.car.steer_angle, |steering| steering.target)
+    }
+
+    // 方向盘向左(delta>0)或向右转一圈
+    pub fn steer_by(&mut self, delta: f32) {
+        match self.steering.as_mut() {
+            Some(steering) => steering.steer_by(delta),
+            None => if delta > 0. {
+                self.car.left_steer();
+            } else {
+                self.car.right_steer();
+            },
+        }
+    }
+
+    // 地图上的静态障碍物、交通参与者和其他车, skip是cars里要排除的那辆, None表示当前操控的车
+    fn obstacles_for(&self, skip: Option<usize>) -> Vec<Obstacle> {
+        let mut obstacles = self.map.obstacles();
+        obstacles.extend(self.agents.iter().map(Agent::obstacle));
+        let mut push_car = |car: &Car| {
+            obstacles.push(Obstacle::Vehicle(car.body));
+            if let Some(trailer) = &car.trailer {
+                obstacles.push(Obstacle::Vehicle(trailer.body()));
+            }
+        };
+        if skip.is_some() {
+            push_car(&self.car);
+        }
+        for (i, state) in self.cars.iter().enumerate() {
+            if Some(i) != skip {
+                push_car(&state.car);
+            }
+        }
+        obstacles
+    }
+
+    // 当前操控的车会碰到的障碍物
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        self.obstacles_for(None)
+    }
+
+    // 传感器能探测到的所有线段
+    pub fn geometry(&self) -> Vec<Segment> {
+        let mut geometry = self.map.lines();
+        for obstacle in self.obstacles().iter() {
+            geometry.extend(obstacle.outline());
+        }
+        geometry
+    }
+
+    // dt为None时激光雷达扫完整圈
+    pub fn update_sensors(&mut self, dt: Option<f32>) {
+        let geometry = self.geometry();
+        self.ultrasonic.update(&self.car, &geometry);
+        if let Some(lidar) = self.lidar.as_mut() {
+            lidar.update(&self.car, &geometry, dt);
+        }
+    }
+
+    // 车辆会给玩家的车和其他交通参与者让行
+    fn update_agents(&mut self, dt: f32) {
+        let mut cars = vec![];
+        for car in std::iter::once(&self.car).chain(self.cars.iter().map(|state| &state.car)) {
+            let body = &car.body;
+            cars.extend_from_slice(&[body.origin, body.lt(), body.rt(), body.lb(), body.rb()]);
+        }
+        let positions: Vec<_> = self.agents.iter().map(|agent| agent.position).collect();
+        for (i, agent) in self.agents.iter_mut().enumerate() {
+            let mut blockers = cars.clone();
+            blockers.extend(positions.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, p)| *p));
+            agent.update(dt, &blockers);
+        }
+    }
+
+    // 以当前速度行驶dt秒, 返回当前操控的车新产生的考试事件
+    pub fn step(&mut self, dt: f32) -> Vec<ExamEvent> {
+        if let Some(steering) = self.steering.as_mut() {
+            steering.step(&mut self.car, self.speed, dt);
+        }
+        self.car.adapt_rear_steer(self.speed);
+        match self.dynamics.as_mut() {
+            Some(model) => model.step(&mut self.car, self.speed, dt),
+            None => if self.speed != 0. {
+                self.car.forward(self.speed*dt);
+            },
+        }
+        for state in self.cars.iter_mut() {
+            state.step(dt);
+        }
+        self.time += dt;
+        self.update_agents(dt);
+        self.update_sensors(Some(dt));
+        for i in 0..self.cars.len() {
+            let obstacles = self.obstacles_for(Some(i));
+            let state = &mut self.cars[i];
+            state.exam.update(&state.car, self.map.as_ref(), &obstacles, state.speed, dt);
+        }
+        let obstacles = self.obstacles();
+        self.exam.update(&self.car, self.map.as_ref(), &obstacles, self.speed, dt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sim;
+    use crate::{Car, MapKind, distance_of, controller::{Command, Controller, TrackingError}, vehicle::VehicleClass};
+
+    // 一直往前开的自动驾驶
+    struct Cruise;
+
+    impl Controller for Cruise {
+        fn control(&mut self, _: &Car) -> Command {
+            Command { steer_angle: 0., speed: 1. }
+        }
+
+        fn error(&self) -> TrackingError {
+            TrackingError::default()
+        }
+
+        fn finished(&self) -> bool {
+            false
+        }
+    }
+
+    // 把当前的车往前开distance米
+    fn drive(sim: &mut Sim, distance: f32) {
+        sim.speed = 2.;
+        for _ in 0..(distance/0.2) as usize {
+            sim.step(0.1);
+        }
+        sim.speed = 0.;
+    }
+
+    #[test]
+    fn switch_keeps_each_car() {
+        let mut sim = Sim::new(MapKind::RightAngleTurn.build(VehicleClass::Car.spec()));
+        let start = sim.car.body.origin;
+        // 起点上还停着车, 不能再放
+        assert!(!sim.add_car());
+        drive(&mut sim, 6.);
+        let first = sim.car.body.origin;
+        let first_score = sim.exam.score();
+        assert!(sim.add_car());
+        assert_eq!(sim.cars.len(), 1);
+        assert!(distance_of(sim.car.body.origin, start) < 1e-4);
+        assert!(distance_of(sim.cars[0].car.body.origin, first) < 1e-4);
+        drive(&mut sim, 1.);
+        let second = sim.car.body.origin;
+        // 切回第一辆车, 位置和考试分数都还在, 没人开的第二辆车停在原地
+        sim.speed = 2.;
+        assert!(sim.switch_car(None).is_none());
+        assert_eq!(sim.speed, 0.);
+        assert!(distance_of(sim.car.body.origin, first) < 1e-4);
+        assert_eq!(sim.exam.score(), first_score);
+        sim.step(0.1);
+        assert!(distance_of(sim.cars[0].car.body.origin, second) < 1e-4);
+    }
+
+    #[test]
+    fn autopilot_keeps_driving() {
+        let mut sim = Sim::new(MapKind::RightAngleTurn.build(VehicleClass::Car.spec()));
+        drive(&mut sim, 6.);
+        assert!(sim.add_car());
+        let parked = sim.cars[0].car.body.origin;
+        // 新车带着自动驾驶切走, 切走后继续往前开
+        assert!(sim.switch_car(Some(Box::new(Cruise))).is_none());
+        let start = sim.cars[0].car.body.origin;
+        for _ in 0..10 {
+            sim.step(0.1);
+        }
+        assert!(distance_of(sim.car.body.origin, parked) < 1e-4);
+        assert!((distance_of(sim.cars[0].car.body.origin, start) - 1.).abs() < 1e-3);
+        // 切回去时把控制器交回来
+        assert!(sim.switch_car(None).is_some());
+    }
+}
@@ -0,0 +1,146 @@
+use font_kit::font::Font;
+use raqote::{DrawTarget, SolidSource, Source, DrawOptions};
+
+use car_simulation::{MapKind, json::Value, practice::{self, Attempt}};
+
+use crate::{i18n::{self, Message}, ui::{self, ListItem, Panel}};
+
+// 柱状图显示最近几个练过车的日子的合格率
+const CHART_DAYS: usize = 10;
+const CHART_HEIGHT: f32 = 120.;
+// 每个项目列出的常见错误数
+const TOP_MISTAKES: usize = 3;
+const BOX_WIDTH: f32 = 520.;
+// 统计画面右边的学员列表
+const TRAINEE_LIST_WIDTH: f32 = 160.;
+
+// 按学员名字保存的练习记录, 文件内容是 {"学员名": [记录, ...], ...}
+pub struct StatsStore {
+    // 为None时只记在内存里, 不保存
+    path: Option<String>,
+    trainees: Vec<(String, Vec<Attempt>)>,
+}
+
+impl StatsStore {
+    // 文件不存在时从空记录开始, 第一次保存时创建
+    pub fn open(path: &str) -> Result<StatsStore, String> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(StatsStore { path: Some(path.to_string()), trainees: vec![] });
+            },
+            Err(err) => return Err(format!("cannot read {}: {}", path, err)),
+        };
+        let trainees = practice::records_from_json(&Value::parse(&text)?)?;
+        Ok(StatsStore { path: Some(path.to_string()), trainees })
+    }
+
+    // 文件坏了时改名成.bad留着, 从空记录开始; 改名也失败就只记在内存里, 免得覆盖原来的记录
+    pub fn recover(path: &str) -> StatsStore {
+        let backup = format!("{}.bad", path);
+        match std::fs::rename(path, &backup) {
+            Ok(()) => {
+                println!("{}", i18n::format(Message::StatsFileMoved, &[&backup]));
+                StatsStore { path: Some(path.to_string()), trainees: vec![] }
+            },
+            Err(err) => {
+                println!("{}", i18n::format(Message::StatsFileStuck, &[&err]));
+                StatsStore { path: None, trainees: vec![] }
+            },
+        }
+    }
+
+    // 有记录的学员, 按第一次练习的顺序
+    pub fn trainees(&self) -> impl Iterator<Item = &str> {
+        self.trainees.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn attempts(&self, trainee: &str) -> &[Attempt] {
+        self.trainees.iter().find(|(name, _)| name == trainee).map_or(&[], |(_, attempts)| attempts.as_slice())
+    }
+
+    // 加一条记录并把整个文件写回去
+    pub fn add(&mut self, trainee: &str, attempt: Attempt) -> std::io::Result<()> {
+        match self.trainees.iter_mut().find(|(name, _)| name == trainee) {
+            Some((_, attempts)) => attempts.push(attempt),
+            None => self.trainees.push((trainee.to_string(), vec![attempt])),
+        }
+        let path = self.path.as_ref().ok_or_else(|| std::io::Error::other("stats file is invalid"))?;
+        std::fs::write(path, practice::records_to_json(&self.trainees).to_string())
+    }
+}
+
+fn percent(attempts: &[&Attempt]) -> String {
+    format!("{:.0}", practice::pass_rate(attempts))
+}
+
+// 最近CHART_DAYS个练过车的日子, 每天一根柱子, 高度是当天的合格率
+fn draw_chart(dt: &mut DrawTarget, font: &Font, attempts: &[Attempt], (left, top): (f32, f32), width: f32) {
+    let days = practice::by_day(attempts);
+    let days = &days[days.len().saturating_sub(CHART_DAYS)..];
+    let height = CHART_HEIGHT + 2.*ui::BOX_MARGIN + 16.;
+    dt.fill_rect(left, top, width, height,
+        &Source::Solid(SolidSource::from_unpremultiplied_argb(0xc0, 0x00, 0x00, 0x00)), &DrawOptions::new());
+    let slot = (width - 2.*ui::BOX_MARGIN)/(CHART_DAYS as f32);
+    let base = top + ui::BOX_MARGIN + CHART_HEIGHT;
+    let white = Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff));
+    for (i, (day, of_day)) in days.iter().enumerate() {
+        let x = left + ui::BOX_MARGIN + slot*(i as f32);
+        let rate = (of_day.iter().filter(|attempt| attempt.passed).count() as f32)/(of_day.len() as f32);
+        dt.fill_rect(x + 4., base - rate*CHART_HEIGHT, slot - 8., rate*CHART_HEIGHT,
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0x66, 0xfc, 0x03)), &DrawOptions::new());
+        let (month, date) = practice::month_day(day*86400);
+        dt.draw_text(font, 12., &format!("{:02}-{:02}", month, date), (x + 4., base + 16.).into(),
+            &white, &DrawOptions::new());
+    }
+}
+
+// 统计文字框的宽度, 右边留出学员列表的位置
+fn box_width(width: f32) -> f32 {
+    (width - 3.*ui::BOX_MARGIN - TRAINEE_LIST_WIDTH).min(BOX_WIDTH)
+}
+
+pub fn trainee_list_left((left, _, width, _): (f32, f32, f32, f32)) -> f32 {
+    left + 2.*ui::BOX_MARGIN + box_width(width)
+}
+
+// 统计画面右边的学员列表, 点一下切换当前学员
+pub fn trainee_list(names: &[String], selected: usize, viewport: (f32, f32, f32, f32)) -> Panel {
+    let mut list = Panel::new(trainee_list_left(viewport), viewport.1 + ui::BOX_MARGIN, TRAINEE_LIST_WIDTH);
+    list.set_background(SolidSource::from_unpremultiplied_argb(0xc0, 0x00, 0x00, 0x00));
+    list.add_list("trainee", names.iter().map(|name| ListItem::Text(name.clone())).collect(), selected);
+    list
+}
+
+// 练习统计画面: 总的和每个项目的合格率、常见错误, 下面是最近每天的合格率
+pub fn draw(dt: &mut DrawTarget, font: &Font, trainee: &str, attempts: &[Attempt],
+    (left, top, width, _): (f32, f32, f32, f32)) {
+    let all: Vec<&Attempt> = attempts.iter().collect();
+    let mut lines = vec![];
+    if all.is_empty() {
+        lines.push(i18n::format(Message::NoAttempts, &[&trainee]));
+    } else {
+        lines.push(i18n::format(Message::StatsSummary, &[&trainee, &all.len(), &percent(&all)]));
+        for &kind in MapKind::ALL.iter() {
+            let of_map: Vec<&Attempt> = all.iter().cloned().filter(|attempt| attempt.map == kind).collect();
+            if of_map.is_empty() {
+                continue;
+            }
+            lines.push(i18n::format(Message::MapStats, &[&i18n::tr(kind.into()), &of_map.len(), &percent(&of_map)]));
+            let mistakes = practice::frequent_mistakes(&of_map, TOP_MISTAKES);
+            if !mistakes.is_empty() {
+                let text = mistakes.iter().map(|&(event, count)| format!("{}×{}", i18n::tr(event.into()), count))
+                    .collect::<Vec<_>>().join(", ");
+                lines.push(i18n::format(Message::FrequentMistakes, &[&text]));
+            }
+        }
+        lines.push(i18n::tr(Message::PassRateByDay).to_string());
+    }
+    let origin = (left + ui::BOX_MARGIN, top + ui::BOX_MARGIN);
+    let box_width = box_width(width);
+    let bottom = ui::draw_text_box(dt, font, origin, box_width, &lines);
+    if !all.is_empty() {
+        draw_chart(dt, font, attempts, (origin.0, bottom), box_width);
+    }
+}
+
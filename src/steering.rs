@@ -0,0 +1,140 @@
+use crate::Car;
+
+// 真车方向盘从回正打到底转的圈数, Car::steer_angle打到底是spec.lock_turns
+pub const WHEEL_TURNS_TO_LOCK: f32 = 1.5;
+
+// Car::steer_angle换算成方向盘实际转过的圈数
+pub fn wheel_turns(angle: f32, lock_turns: f32) -> f32 {
+    angle/lock_turns*WHEEL_TURNS_TO_LOCK
+}
+
+// 目标转角的单位和Car::steer_angle一致, 转速和回正按方向盘实际的圈数(见wheel_turns)
+#[derive(Clone, Copy)]
+pub struct SteeringConfig {
+    // 方向盘最快转速(圈/s)
+    pub max_rate: f32,
+    // 松手时每行驶1m自动回正的圈数, 0表示不回正
+    pub self_align: f32,
+}
+
+impl Default for SteeringConfig {
+    fn default() -> Self {
+        SteeringConfig {
+            max_rate: 1.5,
+            self_align: 0.,
+        }
+    }
+}
+
+// 转向执行机构: 输入只改变目标转角, 车轮按最大转速慢慢转过去
+pub struct SteeringActuator {
+    pub config: SteeringConfig,
+    pub target: f32,
+    // 手是否握着方向盘, 松手且车在走时方向盘回正
+    pub held: bool,
+}
+
+impl SteeringActuator {
+    pub fn new(config: SteeringConfig) -> Self {
+        SteeringActuator {
+            config,
+            target: 0.,
+            held: false,
+        }
+    }
+
+    // 超过方向盘圈数的部分在step里按车的参数截掉
+    pub fn steer_to(&mut self, angle: f32) {
+        self.target = angle;
+    }
+
+    // 同Car::left_steer/right_steer, 每次转一圈
+    pub fn steer_by(&mut self, delta: f32) {
+        self.steer_to(self.target.round() + delta);
+    }
+
+    // 换车或重置后和车的实际转角对齐
+    pub fn sync(&mut self, car: &Car) {
+        self.target = car.steer_angle;
+    }
+
+    // 以speed行驶dt秒, 把car的转角往目标转
+    pub fn step(&mut self, car: &mut Car, speed: f32, dt: f32) {
+        let max = car.spec.lock_turns;
+        self.target = self.target.max(-max).min(max);
+        // 方向盘转一圈对应的steer_angle
+        let per_turn = max/WHEEL_TURNS_TO_LOCK;
+        if !self.held && self.config.self_align > 0. {
+            let back = self.config.self_align*per_turn*speed.abs()*dt;
+            self.target = self.target.signum()*(self.target.abs() - back).max(0.);
+        }
+        let max_delta = self.config.max_rate*per_turn*dt;
+        let delta = (self.target - car.steer_angle).max(-max_delta).min(max_delta);
+        if delta != 0. {
+            car.set_steer_angle(car.steer_angle + delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{point2, vehicle::VehicleClass};
+
+    const DT: f32 = 0.05;
+
+    fn car() -> Car {
+        Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.)
+    }
+
+    #[test]
+    fn rate_limited() {
+        let mut car = car();
+        let max = car.spec.lock_turns;
+        let mut steering = SteeringActuator::new(SteeringConfig::default());
+        steering.steer_to(2.*max);
+        let mut ticks = 0;
+        while car.steer_angle < max {
+            let before = car.steer_angle;
+            steering.step(&mut car, 0., DT);
+            let turned = wheel_turns(car.steer_angle - before, max);
+            assert!(turned > 0. && turned <= steering.config.max_rate*DT + 1e-5);
+            ticks += 1;
+            assert!(ticks < 100);
+        }
+        // 目标超过打满的部分截掉, 从回正打到底用WHEEL_TURNS_TO_LOCK/max_rate秒
+        assert_eq!(steering.target, max);
+        assert_eq!(ticks, (WHEEL_TURNS_TO_LOCK/steering.config.max_rate/DT).ceil() as i32);
+        // 反打同样限速
+        steering.steer_to(-max);
+        let before = car.steer_angle;
+        steering.step(&mut car, 0., DT);
+        assert!((wheel_turns(before - car.steer_angle, max) - steering.config.max_rate*DT).abs() < 1e-5);
+    }
+
+    #[test]
+    fn self_align_only_while_rolling() {
+        let mut car = car();
+        let mut steering = SteeringActuator::new(SteeringConfig {self_align: 0.5, ..SteeringConfig::default()});
+        steering.steer_to(2.);
+        steering.held = true;
+        for _ in 0..40 {
+            steering.step(&mut car, 3., DT);
+        }
+        // 握着方向盘不回正
+        assert_eq!(car.steer_angle, 2.);
+        steering.held = false;
+        for _ in 0..40 {
+            steering.step(&mut car, 0., DT);
+        }
+        // 松手但车停着也不回正
+        assert_eq!(car.steer_angle, 2.);
+        let mut last = car.steer_angle;
+        for _ in 0..200 {
+            steering.step(&mut car, -3., DT);
+            assert!(car.steer_angle <= last && car.steer_angle >= 0.);
+            last = car.steer_angle;
+        }
+        assert_eq!(car.steer_angle, 0.);
+    }
+}
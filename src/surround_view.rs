@@ -0,0 +1,182 @@
+use raqote::{DrawTarget, SolidSource, Source, DrawOptions, PathBuilder, ExtendMode, FilterMode, Transform, StrokeStyle};
+
+use car_simulation::{Car, Point, MENU_WIDTH, SCALE, WINDOW_HEIGHT, new_rotation_matrix, linear_algebra::Vector2D};
+
+const DEG: f32 = std::f32::consts::PI/180.;
+// 辅助线的预测长度和采样间隔
+const GUIDELINE_LENGTH: f32 = 3.;
+const GUIDELINE_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy)]
+pub struct Camera {
+    // 安装位置, 车身坐标系, 单位同UltrasonicSensor::mount
+    pub mount: (f32, f32),
+    // 相对车头方向的角度, 向左为正
+    pub angle: f32,
+    pub fov: f32,
+}
+
+// 显示区域(像素): (left, top, width, height)
+type Viewport = (f32, f32, f32, f32);
+
+// 360°全景影像: 只显示四个鱼眼摄像头能看到的部分, 以车为中心、车头朝上
+pub struct SurroundView {
+    pub cameras: Vec<Camera>,
+    // 显示窗口对应的边长(m)
+    pub window_size: f32,
+}
+
+impl SurroundView {
+    pub fn new() -> Self {
+        let pi = std::f32::consts::PI;
+        SurroundView {
+            cameras: vec![
+                Camera { mount: (0., 1.), angle: 0., fov: 190.*DEG },
+                Camera { mount: (0., -1.), angle: pi, fov: 190.*DEG },
+                // 后视镜下方
+                Camera { mount: (-1., 0.35), angle: pi/2., fov: 190.*DEG },
+                Camera { mount: (1., 0.35), angle: -pi/2., fov: 190.*DEG },
+            ],
+            window_size: 12.,
+        }
+    }
+
+    fn center(&self, (left, top, width, height): Viewport) -> (f32, f32, f32) {
+        let pixels_per_meter = width.min(height)/self.window_size;
+        (left + width/2., top + height/2., pixels_per_meter)
+    }
+
+    // 世界坐标 -> 全景影像中的像素坐标
+    fn to_display(&self, viewport: Viewport, car: &Car, p: Point) -> (f32, f32) {
+        let (center_x, center_y, pixels_per_meter) = self.center(viewport);
+        let local = new_rotation_matrix(-car.heading()) * (p - car.body.origin);
+        (center_x + local.x()*pixels_per_meter, center_y - local.y()*pixels_per_meter)
+    }
+
+    // 全景影像中的像素坐标 -> 场景(scene)中的像素坐标, world是场景里地图的缩放和平移
+    fn scene_transform(&self, viewport: Viewport, car: &Car, world: &Transform) -> Transform {
+        let (center_x, center_y, pixels_per_meter) = self.center(viewport);
+        let heading = car.heading();
+        let (c, s, k) = (f32::cos(heading), f32::sin(heading), 1./pixels_per_meter);
+        let origin = car.body.origin;
+        Transform::row_major(
+            SCALE*c*k, -SCALE*s*k,
+            SCALE*s*k, SCALE*c*k,
+            SCALE*(origin.x + MENU_WIDTH - c*k*center_x - s*k*center_y),
+            SCALE*(WINDOW_HEIGHT - origin.y + s*k*center_x - c*k*center_y),
+        ).post_transform(world)
+    }
+
+    // scene: 已经画好地图和车的完整画面, 地图按world变换过; 全景影像画在viewport里
+    pub fn draw(&self, dt: &mut DrawTarget, scene: &DrawTarget, viewport: Viewport, world: &Transform, car: &Car, speed: f32) {
+        let (center_x, center_y, pixels_per_meter) = self.center(viewport);
+        let (left, top, width, height) = viewport;
+        dt.fill_rect(left, top, width, height,
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0x20, 0x20, 0x20)), &DrawOptions::new());
+
+        // 各摄像头的视野扇形和车身本身
+        let radius = self.window_size;
+        let body = &car.body;
+        let mut pb = PathBuilder::new();
+        for camera in self.cameras.iter() {
+            let local = Vector2D::new_from_x_and_y(camera.mount.0*body.width/2., camera.mount.1*body.height/2.);
+            let origin = body.origin + body.rotation_matrix*local;
+            let (x, y) = self.to_display(viewport, car, origin);
+            pb.move_to(x, y);
+            let steps = 24;
+            for i in 0..=steps {
+                let angle = camera.angle + camera.fov*((i as f32)/(steps as f32) - 0.5);
+                let p = origin + radius*(new_rotation_matrix(angle)*car.direction());
+                let (x, y) = self.to_display(viewport, car, p);
+                pb.line_to(x, y);
+            }
+            pb.close();
+        }
+        for (i, p) in [body.lt(), body.lb(), body.rb(), body.rt()].iter().enumerate() {
+            let (x, y) = self.to_display(viewport, car, *p);
+            if i == 0 {
+                pb.move_to(x, y);
+            } else {
+                pb.line_to(x, y);
+            }
+        }
+        pb.close();
+        dt.push_clip_rect(raqote::IntRect::new(
+            raqote::IntPoint::new(left as i32, top as i32), raqote::IntPoint::new((left+width) as i32, (top+height) as i32)));
+        dt.push_clip(&pb.finish());
+        let image = raqote::Image {
+            width: scene.width(),
+            height: scene.height(),
+            data: scene.get_data(),
+        };
+        dt.fill_rect(left, top, width, height,
+            &Source::Image(image, ExtendMode::Pad, FilterMode::Bilinear, self.scene_transform(viewport, car, world)),
+            &DrawOptions::new());
+        dt.pop_clip();
+        self.draw_guidelines(dt, viewport, car, speed);
+        dt.pop_clip();
+
+        // 显示窗口的比例尺: 1m
+        let (x, y) = (center_x - width/2. + 20., center_y + height/2. - 20.);
+        dt.fill_rect(x - 10., y - 10., pixels_per_meter + 20., 20.,
+            &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0x20, 0x20, 0x20)), &DrawOptions::new());
+        let mut pb = PathBuilder::new();
+        pb.move_to(x, y);
+        pb.line_to(x + pixels_per_meter, y);
+        dt.stroke(&pb.finish(), &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xff, 0xff)),
+            &StrokeStyle { width: 3., ..StrokeStyle::default() }, &DrawOptions::new());
+    }
+
+    // 按当前方向盘角度预测车身两侧的轨迹, 前进时画车头, 倒车或停车时画车尾
+    fn draw_guidelines(&self, dt: &mut DrawTarget, viewport: Viewport, car: &Car, speed: f32) {
+        let forward = speed > 0.;
+        let step = if forward {GUIDELINE_STEP} else {-GUIDELINE_STEP};
+        let corners = |car: &Car| if forward {
+            (car.body.lt(), car.body.rt())
+        } else {
+            (car.body.lb(), car.body.rb())
+        };
+        let mut predicted = car.clone();
+        let mut left = vec![corners(&predicted).0];
+        let mut right = vec![corners(&predicted).1];
+        let steps = (GUIDELINE_LENGTH/GUIDELINE_STEP) as usize;
+        for _ in 0..steps {
+            predicted.forward(step);
+            let (l, r) = corners(&predicted);
+            left.push(l);
+            right.push(r);
+        }
+        let style = StrokeStyle { width: 3., ..StrokeStyle::default() };
+        for side in [&left, &right].iter() {
+            let mut pb = PathBuilder::new();
+            for (i, p) in side.iter().enumerate() {
+                let (x, y) = self.to_display(viewport, car, *p);
+                if i == 0 {
+                    pb.move_to(x, y);
+                } else {
+                    pb.line_to(x, y);
+                }
+            }
+            dt.stroke(&pb.finish(), &Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xd0, 0x00)),
+                &style, &DrawOptions::new());
+        }
+        // 距离标记: 1m红色, 2m黄色, 3m绿色
+        let colors = [
+            SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0x00, 0x00),
+            SolidSource::from_unpremultiplied_argb(0xff, 0xff, 0xd0, 0x00),
+            SolidSource::from_unpremultiplied_argb(0xff, 0x30, 0xc0, 0x30),
+        ];
+        for (meter, color) in colors.iter().enumerate() {
+            let i = (((meter+1) as f32)/GUIDELINE_STEP).round() as usize;
+            if i >= left.len() {
+                break;
+            }
+            let mut pb = PathBuilder::new();
+            let (x, y) = self.to_display(viewport, car, left[i]);
+            pb.move_to(x, y);
+            let (x, y) = self.to_display(viewport, car, right[i]);
+            pb.line_to(x, y);
+            dt.stroke(&pb.finish(), &Source::Solid(*color), &style, &DrawOptions::new());
+        }
+    }
+}
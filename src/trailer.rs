@@ -0,0 +1,198 @@
+use crate::{Canvas, Car, Color, Point, Rect, View, WHEEL_WIDTH, WHEEL_HEIGHT, new_rotation_matrix, geometry::{angle_of, normalize_angle}, linear_algebra::Vector2D};
+
+const DEG: f32 = std::f32::consts::PI/180.;
+
+#[derive(Clone, Copy)]
+pub struct TrailerConfig {
+    // 挂钩在车尾后方的距离
+    pub hitch_offset: f32,
+    // 挂钩到挂车车轴的距离
+    pub wheelbase: f32,
+    // 车厢的长和宽, 车轴在车厢中间
+    pub length: f32,
+    pub width: f32,
+    pub track_width: f32,
+    pub wheel_width: f32,
+    pub wheel_height: f32,
+    // 挂车和牵引车的夹角超过这个角度就折叠(jackknife)了
+    pub jackknife_angle: f32,
+}
+
+impl Default for TrailerConfig {
+    fn default() -> Self {
+        TrailerConfig {
+            hitch_offset: 0.1,
+            wheelbase: 3.2,
+            length: 3.0,
+            width: 1.5,
+            track_width: 1.75,
+            wheel_width: WHEEL_WIDTH,
+            wheel_height: WHEEL_HEIGHT,
+            jackknife_angle: 70.*DEG,
+        }
+    }
+}
+
+// 挂在车后面的单轴挂车, 车轴只能沿挂车朝向移动
+#[derive(Clone, Copy)]
+pub struct Trailer {
+    pub config: TrailerConfig,
+    // 挂车车轴中心
+    pub axle: Point,
+    // 朝向, 同Car::heading
+    pub heading: f32,
+    pub jackknifed: bool,
+}
+
+impl Trailer {
+    // 挂在car后面, 和car在一条直线上
+    pub fn new(config: TrailerConfig, car: &Car) -> Self {
+        let hitch = Trailer::hitch(&config, car);
+        Trailer {
+            config,
+            axle: hitch - config.wheelbase*car.direction(),
+            heading: car.heading(),
+            jackknifed: false,
+        }
+    }
+
+    fn hitch(config: &TrailerConfig, car: &Car) -> Point {
+        car.back_origin() - (car.spec.rear_overhang + config.hitch_offset)*car.direction()
+    }
+
+    fn direction(&self) -> Vector2D {
+        new_rotation_matrix(self.heading) * Vector2D::new_from_x_and_y(0., 1.)
+    }
+
+    // 牵引车相对挂车的夹角, 向左为正
+    pub fn articulation(&self, car: &Car) -> f32 {
+        normalize_angle(car.heading() - self.heading)
+    }
+
+    // car移动以后调用: 车轴沿挂车朝向跟着挂钩走, 保持到挂钩的距离不变
+    pub fn follow(&mut self, car: &Car) {
+        let hitch = Trailer::hitch(&self.config, car);
+        let v = hitch - self.axle;
+        let length = (v.x()*v.x() + v.y()*v.y()).sqrt();
+        if length > 0. {
+            self.heading = angle_of(v) - std::f32::consts::PI/2.;
+        }
+        let articulation = self.articulation(car);
+        self.jackknifed = articulation.abs() >= self.config.jackknife_angle;
+        if self.jackknifed {
+            // 折叠后挂车被牵引车顶住, 夹角不再变大
+            self.heading = normalize_angle(car.heading() - self.config.jackknife_angle*articulation.signum());
+        }
+        self.axle = hitch - self.config.wheelbase*self.direction();
+    }
+
+    pub fn body(&self) -> Rect {
+        let mut body = Rect::new(self.axle, self.config.width, self.config.length,
+            Some(Color::rgb(0x70, 0x80, 0x90)));
+        body.rotate_self(new_rotation_matrix(self.heading));
+        body
+    }
+
+    pub fn wheels(&self) -> [Rect; 2] {
+        let color = Some(Color::rgb(0, 0, 0));
+        let rotation_matrix = new_rotation_matrix(self.heading);
+        let wheel = |side: f32| {
+            let origin = self.axle + rotation_matrix*Vector2D::new_from_x_and_y(side*self.config.track_width/2., 0.);
+            let mut wheel = Rect::new(origin, self.config.wheel_width, self.config.wheel_height, color);
+            wheel.rotate_self(rotation_matrix);
+            wheel
+        };
+        [wheel(-1.), wheel(1.)]
+    }
+}
+
+impl View for Trailer {
+    fn relative_translation(&self) -> Vector2D {
+        (0., 0.).into()
+    }
+
+    // 由Car::draw调用, translation里已经有菜单栏的偏移
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let translation = translation + self.relative_translation();
+        let front = self.axle + (self.config.length/2.)*self.direction();
+        let hitch = self.axle + self.config.wheelbase*self.direction();
+        canvas.stroke_polyline(&[front + translation, hitch + translation], 4., Color::rgb(0x40, 0x40, 0x40));
+        for wheel in self.wheels().iter() {
+            wheel.draw(canvas, translation);
+        }
+        let color = if self.jackknifed {
+            Color::rgb(0xd0, 0x40, 0x40)
+        } else {
+            Color::rgb(0x70, 0x80, 0x90)
+        };
+        let mut body = self.body();
+        body.color = Some(color);
+        body.draw(canvas, translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrailerConfig;
+    use crate::{Car, point2, vehicle::VehicleClass};
+
+    fn car_with_trailer() -> Car {
+        let mut car = Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.);
+        car.set_trailer(Some(TrailerConfig::default()));
+        car
+    }
+
+    #[test]
+    fn straight_reversing() {
+        let mut car = car_with_trailer();
+        for _ in 0..200 {
+            car.forward(-0.05);
+            let trailer = car.trailer.unwrap();
+            assert!(trailer.articulation(&car).abs() < 1e-4);
+            assert!(!trailer.jackknifed);
+        }
+    }
+
+    #[test]
+    fn full_lock_reversing_jackknifes() {
+        let mut car = car_with_trailer();
+        let limit = TrailerConfig::default().jackknife_angle;
+        car.set_steer_angle(car.spec.lock_turns);
+        let mut steps = 0;
+        while !car.trailer.unwrap().jackknifed {
+            let before = car.trailer.unwrap().articulation(&car).abs();
+            car.forward(-0.05);
+            // 倒车打满方向夹角一直变大, 到限位才折叠
+            let after = car.trailer.unwrap().articulation(&car).abs();
+            assert!(after > before || after >= limit - 1e-4);
+            steps += 1;
+            assert!(steps < 1000, "never jackknifed");
+        }
+        for _ in 0..100 {
+            car.forward(-0.05);
+            let trailer = car.trailer.unwrap();
+            assert!(trailer.jackknifed);
+            assert!((trailer.articulation(&car).abs() - limit).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn full_lock_forward_stays_below_limit() {
+        let mut car = car_with_trailer();
+        car.set_steer_angle(-car.spec.lock_turns);
+        for _ in 0..1000 {
+            car.forward(0.05);
+            assert!(!car.trailer.unwrap().jackknifed);
+        }
+    }
+
+    #[test]
+    fn wheels_from_config() {
+        let config = TrailerConfig { wheel_width: 0.3, wheel_height: 0.9, ..TrailerConfig::default() };
+        let mut car = Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.);
+        car.set_trailer(Some(config));
+        for wheel in car.trailer.unwrap().wheels().iter() {
+            assert_eq!((wheel.width, wheel.height), (0.3, 0.9));
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use font_kit::font::Font;
+use raqote::{DrawTarget, SolidSource, Source, DrawOptions, PathBuilder};
+
+use crate::{fonts, i18n::{self, Message}};
+
+const ROW_HEIGHT: f32 = 28.;
+const TOGGLE_HEIGHT: f32 = 22.;
+const SLIDER_HEIGHT: f32 = 36.;
+const SPACING: f32 = 6.;
+const TEXT_SIZE: f32 = 16.;
+const PADDING: f32 = 6.;
+// 盖在地图上的文字框
+const BOX_TEXT_SIZE: f32 = 20.;
+const BOX_LINE_HEIGHT: f32 = 28.;
+pub const BOX_MARGIN: f32 = 16.;
+
+fn solid(r: u8, g: u8, b: u8) -> Source<'static> {
+    Source::Solid(SolidSource::from_unpremultiplied_argb(0xff, r, g, b))
+}
+
+fn fill_rect(dt: &mut DrawTarget, x: f32, y: f32, width: f32, height: f32, source: &Source) {
+    let mut pb = PathBuilder::new();
+    pb.rect(x, y, width, height);
+    dt.fill(&pb.finish(), source, &DrawOptions::new());
+}
+
+enum Kind {
+    Button,
+    // 显示文字里的{}替换成value
+    Label { value: String },
+    Toggle { on: bool },
+    Slider { value: f32, min: f32, max: f32 },
+    List { items: Vec<ListItem>, selected: usize },
+}
+
+// 列表里的一行: 界面文字按当前语言显示, 学员名字这类原样显示
+pub enum ListItem {
+    Message(Message),
+    Text(String),
+}
+
+impl From<Message> for ListItem {
+    fn from(message: Message) -> Self {
+        ListItem::Message(message)
+    }
+}
+
+impl ListItem {
+    fn text(&self) -> &str {
+        match self {
+            ListItem::Message(message) => i18n::tr(*message),
+            ListItem::Text(text) => text,
+        }
+    }
+}
+
+struct Widget {
+    id: &'static str,
+    // 列表没有标题, 为None
+    text: Option<Message>,
+    kind: Kind,
+    top: f32,
+    height: f32,
+}
+
+impl Widget {
+    // 列表按行响应, 其他控件只有一行; 空列表没有可以点的行
+    fn row_at(&self, y: f32) -> Option<usize> {
+        match &self.kind {
+            Kind::List { items, .. } if items.is_empty() => None,
+            Kind::List { items, .. } => Some((((y - self.top)/ROW_HEIGHT) as usize).min(items.len() - 1)),
+            _ => Some(0),
+        }
+    }
+}
+
+// 控件在这一帧产生的事件, 参数是控件的id
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UiEvent {
+    Clicked(&'static str),
+    Toggled(&'static str, bool),
+    Changed(&'static str, f32),
+    Selected(&'static str, usize),
+}
+
+// 一列从上往下排的控件, 坐标都是像素
+// 按钮、开关和列表在鼠标按下又在同一个控件(同一行)上松开时才触发, 按住不放只触发一次
+pub struct Panel {
+    left: f32,
+    top: f32,
+    width: f32,
+    bottom: f32,
+    // 盖在地图上的面板需要背景
+    background: Option<SolidSource>,
+    widgets: Vec<Widget>,
+    // 鼠标所在的控件和行
+    hovered: Option<(usize, usize)>,
+    // 鼠标按下时所在的控件和行
+    pressed: Option<(usize, usize)>,
+    mouse_was_down: bool,
+}
+
+impl Panel {
+    pub fn new(left: f32, top: f32, width: f32) -> Self {
+        Panel {
+            left,
+            top,
+            width,
+            bottom: top,
+            background: None,
+            widgets: vec![],
+            hovered: None,
+            pressed: None,
+            mouse_was_down: false,
+        }
+    }
+
+    fn push(&mut self, id: &'static str, text: Option<Message>, kind: Kind, height: f32) {
+        if !self.widgets.is_empty() {
+            self.bottom += SPACING;
+        }
+        self.widgets.push(Widget { id, text, kind, top: self.bottom, height });
+        self.bottom += height;
+    }
+
+    pub fn add_button(&mut self, id: &'static str, text: Message) {
+        self.push(id, Some(text), Kind::Button, ROW_HEIGHT);
+    }
+
+    pub fn add_label(&mut self, id: &'static str, text: Message) {
+        self.push(id, Some(text), Kind::Label { value: String::new() }, TOGGLE_HEIGHT);
+    }
+
+    pub fn add_toggle(&mut self, id: &'static str, text: Message, on: bool) {
+        self.push(id, Some(text), Kind::Toggle { on }, TOGGLE_HEIGHT);
+    }
+
+    pub fn add_slider(&mut self, id: &'static str, text: Message, min: f32, max: f32, value: f32) {
+        self.push(id, Some(text), Kind::Slider { value, min, max }, SLIDER_HEIGHT);
+    }
+
+    // 每行显示items里的文字
+    pub fn add_list(&mut self, id: &'static str, items: Vec<ListItem>, selected: usize) {
+        let height = ROW_HEIGHT*(items.len() as f32);
+        self.push(id, None, Kind::List { items, selected }, height);
+    }
+
+    // 整个面板左右移动, 用于贴着窗口右边的面板
+    pub fn set_left(&mut self, left: f32) {
+        self.left = left;
+    }
+
+    pub fn set_background(&mut self, color: SolidSource) {
+        self.background = Some(color);
+    }
+
+    // 最后一个控件下边缘的y坐标, 面板下面的内容从这里往下排
+    pub fn bottom(&self) -> f32 {
+        self.bottom
+    }
+
+    fn widget_mut(&mut self, id: &str) -> Option<&mut Kind> {
+        self.widgets.iter_mut().find(|widget| widget.id == id).map(|widget| &mut widget.kind)
+    }
+
+    // 以下用来在面板以外(键盘、远程控制)改了状态以后同步显示
+    pub fn set_label(&mut self, id: &str, text: String) {
+        if let Some(Kind::Label { value }) = self.widget_mut(id) {
+            *value = text;
+        }
+    }
+
+    pub fn set_toggle(&mut self, id: &str, state: bool) {
+        if let Some(Kind::Toggle { on }) = self.widget_mut(id) {
+            *on = state;
+        }
+    }
+
+    pub fn set_value(&mut self, id: &str, v: f32) {
+        if let Some(Kind::Slider { value, min, max }) = self.widget_mut(id) {
+            *value = v.clamp(*min, *max);
+        }
+    }
+
+    pub fn set_selected(&mut self, id: &str, row: usize) {
+        if let Some(Kind::List { selected, .. }) = self.widget_mut(id) {
+            *selected = row;
+        }
+    }
+
+    fn hit(&self, (x, y): (f32, f32)) -> Option<(usize, usize)> {
+        if x < self.left || x > self.left + self.width {
+            return None;
+        }
+        let i = self.widgets.iter().position(|widget| y >= widget.top && y < widget.top + widget.height)?;
+        self.widgets[i].row_at(y).map(|row| (i, row))
+    }
+
+    fn slider_value(&self, x: f32, min: f32, max: f32) -> f32 {
+        let t = ((x - self.left - PADDING)/(self.width - 2.*PADDING)).clamp(0., 1.);
+        min + t*(max - min)
+    }
+
+    // 每帧调用一次, mouse是鼠标的像素坐标, down是左键是否按住
+    pub fn update(&mut self, mouse: (f32, f32), down: bool) -> Vec<UiEvent> {
+        let mut events = vec![];
+        let hit = self.hit(mouse);
+        self.hovered = hit;
+        if down && !self.mouse_was_down {
+            self.pressed = hit;
+        }
+        // 滑块按住以后拖出控件也继续跟着鼠标走
+        if let (true, Some((i, _))) = (down, self.pressed) {
+            let x = mouse.0;
+            let new_value = match self.widgets[i].kind {
+                Kind::Slider { value, min, max } => {
+                    let new_value = self.slider_value(x, min, max);
+                    if new_value != value {Some(new_value)} else {None}
+                },
+                _ => None,
+            };
+            if let Some(new_value) = new_value {
+                self.set_value(self.widgets[i].id, new_value);
+                events.push(UiEvent::Changed(self.widgets[i].id, new_value));
+            }
+        }
+        if !down && self.mouse_was_down {
+            if let Some((i, row)) = self.pressed.take() {
+                if hit == Some((i, row)) {
+                    let widget = &mut self.widgets[i];
+                    match &mut widget.kind {
+                        Kind::Button => events.push(UiEvent::Clicked(widget.id)),
+                        Kind::Toggle { on } => {
+                            *on = !*on;
+                            events.push(UiEvent::Toggled(widget.id, *on));
+                        },
+                        Kind::List { selected, .. } => {
+                            *selected = row;
+                            events.push(UiEvent::Selected(widget.id, row));
+                        },
+                        Kind::Label { .. } | Kind::Slider { .. } => {},
+                    }
+                }
+            }
+        }
+        self.mouse_was_down = down;
+        events
+    }
+
+    pub fn draw(&self, dt: &mut DrawTarget, font: &Font) {
+        let draw_text = |dt: &mut DrawTarget, text: &str, x: f32, y: f32, source: &Source| {
+            dt.draw_text(font, TEXT_SIZE, &fonts::printable(font, text), (x, y).into(), source, &DrawOptions::new());
+        };
+        let white = solid(0xff, 0xff, 0xff);
+        if let Some(color) = self.background {
+            fill_rect(dt, self.left - PADDING, self.top - PADDING,
+                self.width + 2.*PADDING, self.bottom - self.top + 2.*PADDING, &Source::Solid(color));
+        }
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let hovered = self.hovered.filter(|&(h, _)| h == i).map(|(_, row)| row);
+            let pressed = self.pressed.filter(|&(p, _)| p == i).map(|(_, row)| row);
+            let (x, y) = (self.left, widget.top);
+            let caption = || widget.text.map_or("", i18n::tr);
+            // 文字在行里垂直居中
+            let baseline = |top: f32, height: f32| top + height*0.5 + TEXT_SIZE*0.35;
+            match &widget.kind {
+                Kind::Button => {
+                    let background = if pressed.is_some() {
+                        solid(0x40, 0xa0, 0x00)
+                    } else if hovered.is_some() {
+                        solid(0x90, 0xff, 0x50)
+                    } else {
+                        solid(0x66, 0xfc, 0x03)
+                    };
+                    fill_rect(dt, x, y, self.width, widget.height, &background);
+                    draw_text(dt, caption(), x + PADDING, baseline(y, widget.height), &solid(0, 0, 0xff));
+                },
+                Kind::Label { value } => {
+                    let text = widget.text.map_or(String::new(), |text| i18n::format(text, &[value]));
+                    draw_text(dt, &text, x, baseline(y, widget.height), &white);
+                },
+                Kind::Toggle { on } => {
+                    let size = widget.height - 6.;
+                    let border = if hovered.is_some() {solid(0xff, 0xff, 0xff)} else {solid(0xa0, 0xa0, 0xa0)};
+                    fill_rect(dt, x, y + 3., size, size, &border);
+                    let inner = if *on {solid(0x66, 0xfc, 0x03)} else {solid(0x30, 0x30, 0x30)};
+                    fill_rect(dt, x + 2., y + 5., size - 4., size - 4., &inner);
+                    draw_text(dt, caption(), x + size + PADDING, baseline(y, widget.height), &white);
+                },
+                Kind::Slider { value, min, max } => {
+                    let caption = format!("{} {:.2}", caption(), value);
+                    draw_text(dt, &caption, x, y + TEXT_SIZE, &white);
+                    let track_y = y + widget.height - 8.;
+                    let track_width = self.width - 2.*PADDING;
+                    fill_rect(dt, x + PADDING, track_y - 2., track_width, 4., &solid(0x80, 0x80, 0x80));
+                    let knob_x = x + PADDING + track_width*(value - min)/(max - min);
+                    let knob = if pressed.is_some() || hovered.is_some() {solid(0x90, 0xff, 0x50)} else {solid(0x66, 0xfc, 0x03)};
+                    let mut pb = PathBuilder::new();
+                    pb.arc(knob_x, track_y, 6., 0., 2.*std::f32::consts::PI);
+                    dt.fill(&pb.finish(), &knob, &DrawOptions::new());
+                },
+                Kind::List { items, selected } => {
+                    for (row, item) in items.iter().enumerate() {
+                        let top = y + ROW_HEIGHT*(row as f32);
+                        let background = if row == *selected {
+                            Some(solid(0x66, 0xfc, 0x03))
+                        } else if pressed == Some(row) || hovered == Some(row) {
+                            Some(solid(0x50, 0x50, 0x50))
+                        } else {
+                            None
+                        };
+                        if let Some(background) = background {
+                            fill_rect(dt, x, top, self.width, ROW_HEIGHT, &background);
+                        }
+                        let color = if row == *selected {solid(0, 0, 0)} else {solid(0xff, 0xff, 0xff)};
+                        draw_text(dt, item.text(), x + PADDING, baseline(top, ROW_HEIGHT), &color);
+                    }
+                },
+            }
+        }
+    }
+}
+
+// 半透明的框, 里面一行一行写字, 坐标是像素, 返回框下边缘的y坐标
+pub fn draw_text_box(dt: &mut DrawTarget, font: &Font, (left, top): (f32, f32), width: f32, lines: &[String]) -> f32 {
+    let height = BOX_LINE_HEIGHT*(lines.len() as f32) + BOX_MARGIN;
+    dt.fill_rect(left, top, width, height,
+        &Source::Solid(SolidSource::from_unpremultiplied_argb(0xc0, 0x00, 0x00, 0x00)), &DrawOptions::new());
+    for (i, line) in lines.iter().enumerate() {
+        dt.draw_text(font, BOX_TEXT_SIZE, &fonts::printable(font, line), (left + BOX_MARGIN, top + BOX_LINE_HEIGHT*((i + 1) as f32)).into(),
+            &solid(0xff, 0xff, 0xff), &DrawOptions::new());
+    }
+    top + height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: f32 = 10.;
+    const WIDTH: f32 = 100.;
+
+    // 第i个控件第row行的中间
+    fn at(panel: &Panel, i: usize, row: usize) -> (f32, f32) {
+        (LEFT + WIDTH/2., panel.widgets[i].top + ROW_HEIGHT*(row as f32 + 0.5))
+    }
+
+    #[test]
+    fn button_fires_once_on_release() {
+        let mut panel = Panel::new(LEFT, 0., WIDTH);
+        panel.add_button("reset", Message::Reset);
+        let inside = at(&panel, 0, 0);
+        assert!(panel.update(inside, false).is_empty());
+        assert_eq!(panel.hovered, Some((0, 0)));
+        assert!(panel.update(inside, true).is_empty());
+        assert_eq!(panel.pressed, Some((0, 0)));
+        // 按住不放不会一直触发
+        for _ in 0..10 {
+            assert!(panel.update(inside, true).is_empty());
+        }
+        assert_eq!(panel.update(inside, false), vec![UiEvent::Clicked("reset")]);
+        assert!(panel.update(inside, false).is_empty());
+        assert_eq!(panel.pressed, None);
+    }
+
+    #[test]
+    fn release_elsewhere_cancels() {
+        let mut panel = Panel::new(LEFT, 0., WIDTH);
+        panel.add_button("reset", Message::Reset);
+        panel.add_toggle("ultrasonic", Message::Ultrasonic, false);
+        panel.update(at(&panel, 0, 0), true);
+        // 拖到别的控件上或面板外面松开都不算
+        assert!(panel.update(at(&panel, 1, 0), false).is_empty());
+        panel.update(at(&panel, 0, 0), true);
+        assert!(panel.update((LEFT + WIDTH + 1., 5.), false).is_empty());
+        assert_eq!(panel.hovered, None);
+        // 在面板外按下再移进来松开也不算
+        panel.update((0., 0.), true);
+        assert!(panel.update(at(&panel, 1, 0), false).is_empty());
+    }
+
+    #[test]
+    fn toggle_and_list() {
+        let mut panel = Panel::new(LEFT, 0., WIDTH);
+        panel.add_toggle("ultrasonic", Message::Ultrasonic, false);
+        panel.add_list("map", vec![Message::BackParking.into(), Message::ParallelParking.into()], 0);
+        let toggle = at(&panel, 0, 0);
+        panel.update(toggle, true);
+        assert_eq!(panel.update(toggle, false), vec![UiEvent::Toggled("ultrasonic", true)]);
+        panel.update(toggle, true);
+        assert_eq!(panel.update(toggle, false), vec![UiEvent::Toggled("ultrasonic", false)]);
+        let row = at(&panel, 1, 1);
+        panel.update(row, true);
+        assert_eq!(panel.hovered, Some((1, 1)));
+        assert_eq!(panel.update(row, false), vec![UiEvent::Selected("map", 1)]);
+        // 按下和松开在同一个列表的不同行不算
+        panel.update(at(&panel, 1, 0), true);
+        assert!(panel.update(row, false).is_empty());
+    }
+
+    #[test]
+    fn slider_follows_drag() {
+        let mut panel = Panel::new(LEFT, 0., WIDTH);
+        panel.add_slider("speed", Message::Speed, 0., 10., 5.);
+        let y = panel.widgets[0].top + 1.;
+        assert!(panel.update((LEFT + WIDTH/2., y), false).is_empty());
+        assert_eq!(panel.update((LEFT + PADDING, y), true), vec![UiEvent::Changed("speed", 0.)]);
+        // 不动不产生事件, 拖出面板以后按边界取值
+        assert!(panel.update((LEFT + PADDING, y), true).is_empty());
+        assert_eq!(panel.update((LEFT + 2.*WIDTH, y + 100.), true), vec![UiEvent::Changed("speed", 10.)]);
+        assert!(panel.update((LEFT + WIDTH/2., y), false).is_empty());
+    }
+
+    #[test]
+    fn empty_list_and_unknown_ids() {
+        let mut panel = Panel::new(LEFT, 0., WIDTH);
+        panel.add_list("empty", vec![], 0);
+        let widget = &panel.widgets[0];
+        assert_eq!(widget.row_at(widget.top), None);
+        assert!(panel.update((LEFT + 1., 0.), true).is_empty());
+        assert!(panel.update((LEFT + 1., 0.), false).is_empty());
+        // 没有的控件id忽略
+        panel.set_toggle("missing", true);
+        panel.set_value("missing", 1.);
+    }
+}
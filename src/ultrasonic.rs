@@ -0,0 +1,223 @@
+use crate::{Canvas, Car, Color, Point, View, MENU_WIDTH, new_rotation_matrix, geometry::{Segment, ray_cast}, linear_algebra::Vector2D};
+
+const MAX_RANGE: f32 = 2.5;
+const BEAM_WIDTH: f32 = 50./180.*std::f32::consts::PI;
+// 每个探头用几条射线近似波束
+const BEAM_RAYS: usize = 5;
+const DEG: f32 = std::f32::consts::PI/180.;
+
+#[derive(Clone, Copy)]
+pub struct UltrasonicSensor {
+    // 安装位置, 车身坐标系(x向右, y向前), 以车身半宽/半长为单位, 即保险杠上y=±1
+    pub mount: (f32, f32),
+    // 相对车头方向的角度, 向左为正
+    pub angle: f32,
+    pub max_range: f32,
+    pub beam_width: f32,
+}
+
+impl UltrasonicSensor {
+    pub fn new(mount: (f32, f32), angle: f32) -> Self {
+        UltrasonicSensor {
+            mount,
+            angle,
+            max_range: MAX_RANGE,
+            beam_width: BEAM_WIDTH,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Reading {
+    pub origin: Point,
+    pub direction: Vector2D,
+    // 没有探测到障碍物时为max_range
+    pub distance: f32,
+    pub max_range: f32,
+    pub beam_width: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Zone {
+    Clear,
+    Far,
+    Middle,
+    Near,
+    Stop,
+}
+
+impl Zone {
+    fn of(distance: f32, max_range: f32) -> Zone {
+        if distance < 0.3 {
+            Zone::Stop
+        } else if distance < 0.6 {
+            Zone::Near
+        } else if distance < 1.0 {
+            Zone::Middle
+        } else if distance < max_range {
+            Zone::Far
+        } else {
+            Zone::Clear
+        }
+    }
+
+    // 蜂鸣间隔(s), None表示不响, 0表示长鸣
+    pub fn beep_interval(&self) -> Option<f32> {
+        match self {
+            Zone::Clear => None,
+            Zone::Far => Some(0.6),
+            Zone::Middle => Some(0.3),
+            Zone::Near => Some(0.15),
+            Zone::Stop => Some(0.),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Zone::Clear => Color::rgb(0x80, 0x80, 0x80),
+            Zone::Far => Color::rgb(0x30, 0xc0, 0x30),
+            Zone::Middle => Color::rgb(0xf0, 0xd0, 0x00),
+            Zone::Near => Color::rgb(0xff, 0x80, 0x00),
+            Zone::Stop => Color::rgb(0xff, 0x00, 0x00),
+        }
+    }
+}
+
+// 前后保险杠上的倒车雷达
+pub struct Ultrasonic {
+    pub sensors: Vec<UltrasonicSensor>,
+    readings: Vec<Reading>,
+}
+
+impl Ultrasonic {
+    pub fn new(sensors: Vec<UltrasonicSensor>) -> Self {
+        Ultrasonic { sensors, readings: vec![] }
+    }
+
+    // 前后各4个探头, 两侧的探头朝外偏
+    pub fn bumpers() -> Self {
+        let pi = std::f32::consts::PI;
+        Ultrasonic::new(vec![
+            UltrasonicSensor::new((-0.9, 1.), 40.*DEG),
+            UltrasonicSensor::new((-0.35, 1.), 10.*DEG),
+            UltrasonicSensor::new((0.35, 1.), -10.*DEG),
+            UltrasonicSensor::new((0.9, 1.), -40.*DEG),
+            UltrasonicSensor::new((-0.9, -1.), pi-40.*DEG),
+            UltrasonicSensor::new((-0.35, -1.), pi-10.*DEG),
+            UltrasonicSensor::new((0.35, -1.), pi+10.*DEG),
+            UltrasonicSensor::new((0.9, -1.), pi+40.*DEG),
+        ])
+    }
+
+    pub fn update(&mut self, car: &Car, geometry: &[Segment]) {
+        let body = &car.body;
+        self.readings = self.sensors.iter().map(|sensor| {
+            let local = Vector2D::new_from_x_and_y(sensor.mount.0*body.width/2., sensor.mount.1*body.height/2.);
+            let origin = body.origin + body.rotation_matrix*local;
+            let direction = new_rotation_matrix(sensor.angle) * car.direction();
+            let distance = (0..BEAM_RAYS).map(|i| {
+                let offset = sensor.beam_width*((i as f32)/((BEAM_RAYS-1) as f32) - 0.5);
+                ray_cast(origin, new_rotation_matrix(offset)*direction, geometry, sensor.max_range)
+            }).fold(sensor.max_range, f32::min);
+            Reading {
+                origin,
+                direction,
+                distance,
+                max_range: sensor.max_range,
+                beam_width: sensor.beam_width,
+            }
+        }).collect();
+    }
+
+    pub fn distances(&self) -> Vec<f32> {
+        self.readings.iter().map(|r| r.distance).collect()
+    }
+
+    // 最近的探头决定蜂鸣频率
+    pub fn zone(&self) -> Zone {
+        self.readings.iter()
+            .map(|r| Zone::of(r.distance, r.max_range))
+            .max_by_key(|zone| *zone as i32)
+            .unwrap_or(Zone::Clear)
+    }
+}
+
+impl View for Ultrasonic {
+    fn relative_translation(&self) -> Vector2D {
+        (MENU_WIDTH, 0.).into()
+    }
+
+    // 每个探头画一个扇形, 半径为探测到的距离
+    fn draw(&self, canvas: &mut dyn Canvas, translation: Vector2D) {
+        let translation = translation + self.relative_translation();
+        for reading in self.readings.iter() {
+            let zone = Zone::of(reading.distance, reading.max_range);
+            if zone == Zone::Clear {
+                continue;
+            }
+            let mut points = vec![reading.origin + translation];
+            for i in 0..BEAM_RAYS {
+                let offset = reading.beam_width*((i as f32)/((BEAM_RAYS-1) as f32) - 0.5);
+                points.push(reading.origin + reading.distance*(new_rotation_matrix(offset)*reading.direction) + translation);
+            }
+            // 半透明
+            canvas.fill_polygon(&points, Color { a: 0x80, ..zone.color() });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_RANGE, Ultrasonic, Zone};
+    use crate::{Car, point2, geometry::Segment, vehicle::VehicleClass};
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn nothing_in_range() {
+        let car = Car::new(VehicleClass::Car.spec(), point2(0., 0.), 0.);
+        let mut ultrasonic = Ultrasonic::bumpers();
+        ultrasonic.update(&car, &[]);
+        assert_eq!(ultrasonic.distances(), vec![MAX_RANGE; 8]);
+        assert_eq!(ultrasonic.zone(), Zone::Clear);
+    }
+
+    #[test]
+    fn wall_ahead() {
+        let spec = VehicleClass::Car.spec();
+        let car = Car::new(spec, point2(0., 0.), 0.);
+        // 车头前0.5m横着一堵墙
+        let y = spec.length/2. + 0.5;
+        let wall = Segment::new(point2(-5., y), point2(5., y));
+        let mut ultrasonic = Ultrasonic::bumpers();
+        ultrasonic.update(&car, &[wall]);
+        let distances = ultrasonic.distances();
+        // 中间两个探头偏10°, 最靠近正前方的射线偏2.5°
+        let middle = 0.5/f32::cos(2.5_f32.to_radians());
+        assert_near(distances[1], middle);
+        assert_near(distances[2], middle);
+        // 两侧探头偏40°, 波束边缘偏15°
+        assert_near(distances[0], 0.5/f32::cos(15_f32.to_radians()));
+        assert_near(distances[3], 0.5/f32::cos(15_f32.to_radians()));
+        assert_eq!(&distances[4..], &[MAX_RANGE; 4]);
+        assert_eq!(ultrasonic.zone(), Zone::Near);
+        assert_eq!(ultrasonic.zone().beep_interval(), Some(0.15));
+    }
+
+    #[test]
+    fn wall_behind_rotated_car() {
+        let spec = VehicleClass::Car.spec();
+        // 车头朝左(-x), 车尾后面0.2m有墙
+        let car = Car::new(spec, point2(0., 0.), std::f32::consts::PI/2.);
+        let x = spec.length/2. + 0.2;
+        let wall = Segment::new(point2(x, -5.), point2(x, 5.));
+        let mut ultrasonic = Ultrasonic::bumpers();
+        ultrasonic.update(&car, &[wall]);
+        let distances = ultrasonic.distances();
+        assert_eq!(&distances[..4], &[MAX_RANGE; 4]);
+        assert_near(distances[5], 0.2/f32::cos(2.5_f32.to_radians()));
+        assert_eq!(ultrasonic.zone(), Zone::Stop);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Color, CAR_WIDTH, CAR_HEIGHT, TRACK_WIDTH, FRONT_SUSPENSION, REAR_SUSPENSION, WHEEL_WIDTH, WHEEL_HEIGHT,
+    MIRROR_WIDTH, MIRROR_HEIGHT, MIRROR_ANGLE, MIRROR_ORIGIN_TO_FRONT, TURNING_RADIUS, TURNING_COUNT};
+
+// 设置面板调尺寸时允许的最短轴距
+const MIN_WHEELBASE: f32 = 1.;
+
+// 车的尺寸, Car::new按这个生成车身、车轮和后视镜
+#[derive(Clone, Copy)]
+pub struct VehicleSpec {
+    pub width: f32,
+    pub length: f32,
+    pub track_width: f32,
+    // 前轴到车头的距离
+    pub front_overhang: f32,
+    // 后轴到车尾的距离, 双后轴时是两根后轴中间的虚拟后轴
+    pub rear_overhang: f32,
+    // 双后轴的轴距, 0表示只有一根后轴
+    pub tandem_spacing: f32,
+    pub wheel_width: f32,
+    pub wheel_height: f32,
+    pub mirror_width: f32,
+    pub mirror_height: f32,
+    pub mirror_angle: f32,
+    pub mirror_to_front: f32,
+    pub turning_radius: f32,
+    // 方向盘从回正打到底的圈数, 决定转向比
+    pub lock_turns: f32,
+    pub color: Color,
+}
+
+impl VehicleSpec {
+    // 后视镜展开后两侧伸出车身的总宽度
+    pub fn mirror_span(&self) -> f32 {
+        self.mirror_height*2.*f32::sin(self.mirror_angle)
+    }
+
+    // 设置面板改尺寸以后保证几何关系成立: 轴距不能太短, 车轮不能伸出车身, 打满方向时内侧后轮不能越过转向中心
+    pub fn clamp(&mut self) {
+        self.track_width = self.track_width.min(self.width - self.wheel_width);
+        self.front_overhang = self.front_overhang.min(self.length - MIN_WHEELBASE - self.rear_overhang);
+        let wheelbase = self.length - self.front_overhang - self.rear_overhang;
+        self.turning_radius = self.turning_radius.max(f32::hypot(wheelbase, self.track_width) + 0.1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VehicleClass {
+    // 小型汽车(C1)
+    Car,
+    // 三轴大货车(B2), 两根后轴
+    Truck,
+    // 大客车(A1)
+    Bus,
+}
+
+impl VehicleClass {
+    pub const ALL: [VehicleClass; 3] = [VehicleClass::Car, VehicleClass::Truck, VehicleClass::Bus];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VehicleClass::Car => "car",
+            VehicleClass::Truck => "truck",
+            VehicleClass::Bus => "bus",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VehicleClass> {
+        VehicleClass::ALL.iter().cloned().find(|class| class.name() == name)
+    }
+
+    pub fn spec(self) -> VehicleSpec {
+        match self {
+            VehicleClass::Car => VehicleSpec {
+                width: CAR_WIDTH,
+                length: CAR_HEIGHT,
+                track_width: TRACK_WIDTH,
+                front_overhang: FRONT_SUSPENSION,
+                rear_overhang: REAR_SUSPENSION,
+                tandem_spacing: 0.,
+                wheel_width: WHEEL_WIDTH,
+                wheel_height: WHEEL_HEIGHT,
+                mirror_width: MIRROR_WIDTH,
+                mirror_height: MIRROR_HEIGHT,
+                mirror_angle: MIRROR_ANGLE,
+                mirror_to_front: MIRROR_ORIGIN_TO_FRONT,
+                turning_radius: TURNING_RADIUS,
+                lock_turns: TURNING_COUNT as f32,
+                color: Color::rgb(24, 174, 219),
+            },
+            VehicleClass::Truck => VehicleSpec {
+                width: 2.5,
+                length: 9.0,
+                track_width: 2.0,
+                front_overhang: 1.4,
+                rear_overhang: 2.3,
+                tandem_spacing: 1.35,
+                wheel_width: 0.3,
+                wheel_height: 1.0,
+                mirror_width: 0.1,
+                mirror_height: 0.55,
+                mirror_angle: MIRROR_ANGLE,
+                mirror_to_front: 0.4,
+                turning_radius: 9.5,
+                lock_turns: TURNING_COUNT as f32,
+                color: Color::rgb(0xe0, 0x80, 0x20),
+            },
+            VehicleClass::Bus => VehicleSpec {
+                width: 2.5,
+                length: 10.0,
+                track_width: 2.05,
+                front_overhang: 2.2,
+                rear_overhang: 2.8,
+                tandem_spacing: 0.,
+                wheel_width: 0.3,
+                wheel_height: 1.0,
+                mirror_width: 0.1,
+                mirror_height: 0.55,
+                mirror_angle: MIRROR_ANGLE,
+                mirror_to_front: 0.3,
+                turning_radius: 9.0,
+                lock_turns: TURNING_COUNT as f32,
+                color: Color::rgb(0x40, 0xb0, 0x60),
+            },
+        }
+    }
+}
+
+// 后轮主动转向: 后轮转角和前轮转角之比(正切)随车速变化, 负数为反相, 正数为同相
+#[derive(Clone, Copy)]
+pub struct RearSteerConfig {
+    // 静止时的反相比例, 缩小转弯半径
+    pub counter_phase: f32,
+    // 高速时的同相比例, 变道更稳
+    pub in_phase: f32,
+    // 车速到这个值时比例为0, 两倍时达到in_phase
+    pub crossover_speed: f32,
+}
+
+impl Default for RearSteerConfig {
+    fn default() -> Self {
+        RearSteerConfig {
+            counter_phase: -0.3,
+            in_phase: 0.15,
+            crossover_speed: 8.,
+        }
+    }
+}
+
+impl RearSteerConfig {
+    pub fn ratio(&self, speed: f32) -> f32 {
+        let t = speed.abs()/self.crossover_speed;
+        if t < 1. {
+            self.counter_phase*(1.-t)
+        } else {
+            self.in_phase*(t-1.).min(1.)
+        }
+    }
+}